@@ -16,6 +16,12 @@ pub enum OllamaError {
     #[error("JSON parsing error: {0}")]
     Json(#[from] serde_json::Error),
     
+    #[error("TLS error: {0}")]
+    Tls(#[from] native_tls::Error),
+    
+    #[error("GeoIP database error: {0}")]
+    GeoIp(#[from] maxminddb::MaxMindDBError),
+    
     #[error("Parse error: {0}")]
     ParseError(String),
     
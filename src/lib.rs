@@ -1,3 +1,4 @@
+pub mod report;
 pub mod scanner;
 pub mod storage;
 pub mod utils;
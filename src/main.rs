@@ -1,46 +1,464 @@
-use clap::{Arg, Command};
-use env_logger;
+use clap::{Arg, ArgAction, ArgMatches, Command};
 use log::error;
+use std::net::IpAddr;
 use std::process;
+use std::time::Duration;
 
 use free_ollama::{
-    utils::CsvParser,
-    scanner::SimpleScanner,
+    utils::{CsvParser, ColumnMapping, GeoIpEnricher, unmapped_countries},
+    scanner::{parse_duration, AdaptiveTimeoutConfig, BannerCheck, CredentialStore, DetectorRegistry, ProxyConfig, TemplateDetector, RedirectPolicy, RetryPolicy, ScannerConfig, SimpleScanner, SourceAddress, TimeoutConfig, TlsConfig},
+    report::{DisclosureReporter, DisclosureTemplates, OwnerMapping},
+    storage::FailureKind,
 };
 
+/// 解析 `Name: value` 格式的请求头
+fn parse_header(value: &str) -> Result<(String, String), String> {
+    match value.split_once(':') {
+        Some((name, value)) if !name.trim().is_empty() => Ok((name.trim().to_string(), value.trim().to_string())),
+        _ => Err(format!("expected 'Name: value', got '{}'", value)),
+    }
+}
+
+fn open_enricher(matches: &ArgMatches) -> Option<GeoIpEnricher> {
+    let paths: Vec<&String> = matches.get_many::<String>("mmdb")?.collect();
+    match GeoIpEnricher::open(&paths) {
+        Ok(enricher) => Some(enricher),
+        Err(e) => {
+            error!("Failed to open GeoIP database: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn run_report(matches: &ArgMatches) {
+    let result_files: Vec<&String> = matches.get_many::<String>("results").unwrap().collect();
+    let services = match DisclosureReporter::load_results(&result_files) {
+        Ok(services) => services,
+        Err(e) => {
+            error!("Failed to load scan results: {}", e);
+            process::exit(1);
+        }
+    };
+
+    let owners = matches.get_one::<String>("owners").map(|path| {
+        OwnerMapping::from_csv(path).unwrap_or_else(|e| {
+            error!("Failed to load owner mapping: {}", e);
+            process::exit(1);
+        })
+    });
+
+    let templates = match matches.get_one::<String>("templates") {
+        Some(dir) => DisclosureTemplates::from_dir(dir).unwrap_or_else(|e| {
+            error!("Failed to load report templates: {}", e);
+            process::exit(1);
+        }),
+        None => DisclosureTemplates::default(),
+    };
+
+    let reporter = DisclosureReporter {
+        owners,
+        enricher: open_enricher(matches),
+        templates,
+    };
+
+    let output_dir = matches.get_one::<String>("output").unwrap();
+    match reporter.write_reports(services, output_dir) {
+        Ok(files) => println!("Wrote {} owner reports to {}", files.len(), output_dir),
+        Err(e) => {
+            error!("Failed to write reports: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() {
     env_logger::init();
     
+    let mmdb_arg = Arg::new("mmdb")
+        .long("mmdb")
+        .value_name("FILE")
+        .action(ArgAction::Append)
+        .help("MaxMind/DB-IP .mmdb file for offline country/ASN enrichment (repeatable)");
+
     let app = Command::new("free-ollama")
         .version("0.1.0")
         .about("A simple tool for discovering Ollama services")
+        .subcommand_negates_reqs(true)
+        .subcommand(
+            Command::new("report")
+                .about("Write one responsible-disclosure report per network owner")
+                .arg(
+                    Arg::new("results")
+                        .short('r')
+                        .long("results")
+                        .value_name("FILE")
+                        .action(ArgAction::Append)
+                        .num_args(1..)
+                        .required(true)
+                        .help("Scan results JSON files (repeatable); *_summary.json files are skipped")
+                )
+                .arg(
+                    Arg::new("owners")
+                        .long("owners")
+                        .value_name("FILE")
+                        .help("CSV mapping networks to owners (network,asn,organization)")
+                )
+                .arg(mmdb_arg.clone())
+                .arg(
+                    Arg::new("templates")
+                        .long("templates")
+                        .value_name("DIR")
+                        .help("Directory with disclosure_report.md / disclosure_endpoint.md templates")
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("DIR")
+                        .default_value("reports")
+                        .help("Output directory for the reports")
+                )
+        )
         .arg(
             Arg::new("input")
                 .short('i')
                 .long("input")
                 .value_name("FILE")
-                .help("Path to Ollama assets CSV file (country,link format)")
+                .help("Path to Ollama assets CSV file (country,link format), .gz/.zst supported, '-' for stdin")
                 .required(true)
         )
         .arg(
             Arg::new("timeout")
                 .short('t')
                 .long("timeout")
-                .value_name("SECS")
-                .help("Request timeout in seconds")
+                .value_name("DURATION")
+                .value_parser(parse_duration)
                 .default_value("3")
+                .help("Total request timeout, e.g. 3, 1.5s or 800ms (plain numbers are seconds)")
+        )
+        .arg(
+            Arg::new("connect-timeout")
+                .long("connect-timeout")
+                .value_name("DURATION")
+                .value_parser(parse_duration)
+                .help("DNS lookup and TCP connect timeout [default: same as --timeout]")
+        )
+        .arg(
+            Arg::new("tls-timeout")
+                .long("tls-timeout")
+                .value_name("DURATION")
+                .value_parser(parse_duration)
+                .help("TLS handshake timeout for HTTPS targets [default: same as --timeout]")
+        )
+        .arg(
+            Arg::new("adaptive-timeouts")
+                .long("adaptive-timeouts")
+                .action(ArgAction::SetTrue)
+                .help("Adjust connect/TLS timeouts per /16 (IPv6 /48) from observed connect RTT")
+        )
+        .arg(
+            Arg::new("adaptive-min")
+                .long("adaptive-min")
+                .value_name("DURATION")
+                .value_parser(parse_duration)
+                .default_value("200ms")
+                .help("Lower bound for adaptive timeouts")
+        )
+        .arg(
+            Arg::new("adaptive-max")
+                .long("adaptive-max")
+                .value_name("DURATION")
+                .value_parser(parse_duration)
+                .default_value("5s")
+                .help("Upper bound for adaptive timeouts")
+        )
+        .arg(
+            Arg::new("column-map")
+                .long("column-map")
+                .value_name("FILE")
+                .help("TOML file mapping CSV columns (url, host, port, scheme, country)")
+        )
+        .arg(
+            Arg::new("url-column")
+                .long("url-column")
+                .value_name("NAME")
+                .help("CSV column holding the target URL [default: link]")
+        )
+        .arg(
+            Arg::new("host-column")
+                .long("host-column")
+                .value_name("NAME")
+                .help("CSV column holding the host, used when no URL is present")
+        )
+        .arg(
+            Arg::new("port-column")
+                .long("port-column")
+                .value_name("NAME")
+                .help("CSV column holding the port")
+        )
+        .arg(
+            Arg::new("scheme-column")
+                .long("scheme-column")
+                .value_name("NAME")
+                .help("CSV column holding the scheme (http/https)")
+        )
+        .arg(
+            Arg::new("country-column")
+                .long("country-column")
+                .value_name("NAME")
+                .help("CSV column holding the country [default: country]")
+        )
+        .arg(
+            Arg::new("vhost-column")
+                .long("vhost-column")
+                .value_name("NAME")
+                .help("CSV column holding the virtual host name sent as Host header and TLS SNI")
+        )
+        .arg(mmdb_arg)
+        .arg(
+            Arg::new("retries")
+                .long("retries")
+                .value_name("N")
+                .value_parser(clap::value_parser!(u32).range(0..=100))
+                .default_value("0")
+                .help("Retries per target after a retryable failure (0-100)")
+        )
+        .arg(
+            Arg::new("retry-backoff")
+                .long("retry-backoff")
+                .value_name("MS")
+                .value_parser(clap::value_parser!(u64))
+                .default_value("200")
+                .help("Initial retry backoff in milliseconds, doubled per attempt with jitter")
+        )
+        .arg(
+            Arg::new("retry-max-backoff")
+                .long("retry-max-backoff")
+                .value_name("MS")
+                .value_parser(clap::value_parser!(u64))
+                .default_value("5000")
+                .help("Upper bound for the retry backoff in milliseconds")
+        )
+        .arg(
+            Arg::new("retry-on")
+                .long("retry-on")
+                .value_name("KINDS")
+                .value_delimiter(',')
+                .value_parser(clap::value_parser!(FailureKind))
+                .help("Failure kinds to retry [default: connect_timeout,read_timeout,connection_reset]")
+        )
+        .arg(
+            Arg::new("concurrency")
+                .long("concurrency")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .help("Maximum number of requests in flight (unlimited by default); with --banner-check applies to the deep phase")
+        )
+        .arg(
+            Arg::new("probe-ps")
+                .long("probe-ps")
+                .action(ArgAction::SetTrue)
+                .help("Also query /api/ps on Ollama services to record currently loaded models")
+        )
+        .arg(
+            Arg::new("enrich-models")
+                .long("enrich-models")
+                .action(ArgAction::SetTrue)
+                .help("Query /api/show for each model on Ollama services to record context length, capabilities, license and parameters")
+        )
+        .arg(
+            Arg::new("show-limit")
+                .long("show-limit")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("10")
+                .help("Maximum number of /api/show requests per host")
+        )
+        .arg(
+            Arg::new("banner-check")
+                .long("banner-check")
+                .action(ArgAction::SetTrue)
+                .help("Two-phase scan: check GET / for the Ollama banner first and fully probe only the hosts that pass")
+        )
+        .arg(
+            Arg::new("banner-timeout")
+                .long("banner-timeout")
+                .value_name("DURATION")
+                .value_parser(parse_duration)
+                .default_value("1s")
+                .help("Timeout for the banner check, including connect")
+        )
+        .arg(
+            Arg::new("banner-concurrency")
+                .long("banner-concurrency")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .help("Maximum number of banner checks in flight (unlimited by default)")
+        )
+        .arg(
+            Arg::new("rate")
+                .long("rate")
+                .value_name("RPS")
+                .value_parser(clap::value_parser!(u32))
+                .help("Maximum requests per second across the scan, retries included")
+        )
+        .arg(
+            Arg::new("scheme-fallback")
+                .long("scheme-fallback")
+                .action(ArgAction::SetTrue)
+                .help("Retry with the other scheme (HTTP/HTTPS) when the first probe fails")
+        )
+        .arg(
+            Arg::new("max-redirects")
+                .long("max-redirects")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("10")
+                .help("Maximum redirects to follow, 0 disables following")
+        )
+        .arg(
+            Arg::new("cross-host-redirects")
+                .long("cross-host-redirects")
+                .action(ArgAction::SetTrue)
+                .help("Also follow redirects to other hosts; the result records the host that answered")
+        )
+        .arg(
+            Arg::new("ca-cert")
+                .long("ca-cert")
+                .value_name("FILE")
+                .action(ArgAction::Append)
+                .help("Additional trusted root certificates (PEM bundle), can be repeated")
+        )
+        .arg(
+            Arg::new("client-cert")
+                .long("client-cert")
+                .value_name("FILE")
+                .requires("client-key")
+                .help("Client certificate for mutual TLS (PEM)")
+        )
+        .arg(
+            Arg::new("client-key")
+                .long("client-key")
+                .value_name("FILE")
+                .requires("client-cert")
+                .help("Private key for the client certificate (PKCS#8 PEM)")
+        )
+        .arg(
+            Arg::new("insecure")
+                .long("insecure")
+                .action(ArgAction::SetTrue)
+                .help("Accept invalid, expired and self-signed certificates (DANGEROUS)")
+        )
+        .arg(
+            Arg::new("proxy")
+                .long("proxy")
+                .value_name("URL")
+                .help("Proxy for all requests: http://, https://, socks5:// or socks5h://, overrides HTTP_PROXY/HTTPS_PROXY/ALL_PROXY")
+        )
+        .arg(
+            Arg::new("proxy-user")
+                .long("proxy-user")
+                .value_name("USER:PASS")
+                .help("Proxy credentials, applied to the configured or environment proxy")
+        )
+        .arg(
+            Arg::new("no-proxy")
+                .long("no-proxy")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("proxy")
+                .help("Connect directly, ignoring proxy environment variables")
+        )
+        .arg(
+            Arg::new("source-address")
+                .long("source-address")
+                .value_name("IP")
+                .value_parser(clap::value_parser!(IpAddr))
+                .help("Local address to bind outgoing connections to")
+        )
+        .arg(
+            Arg::new("interface")
+                .long("interface")
+                .value_name("NAME")
+                .conflicts_with("source-address")
+                .help("Bind outgoing connections to the address of this network interface")
+        )
+        .arg(
+            Arg::new("user-agent")
+                .long("user-agent")
+                .value_name("UA")
+                .help("User-Agent sent with every probe [default: free-ollama/<version>]")
+        )
+        .arg(
+            Arg::new("header")
+                .short('H')
+                .long("header")
+                .value_name("NAME: VALUE")
+                .action(ArgAction::Append)
+                .value_parser(parse_header)
+                .help("Extra header sent with every probe, e.g. a contact URL or email, can be repeated")
+        )
+        .arg(
+            Arg::new("credentials")
+                .long("credentials")
+                .value_name("FILE")
+                .help("TOML file with bearer/basic credentials per host, domain or network")
+        )
+        .arg(
+            Arg::new("detectors")
+                .long("detectors")
+                .value_name("NAMES")
+                .value_delimiter(',')
+                .default_value("none")
+                .help("Other LLM servers to recognize when a target is not Ollama: none, all, or a list of llama.cpp,vllm,tgi,lm-studio,localai,openai-compatible")
+        )
+        .arg(
+            Arg::new("templates")
+                .long("templates")
+                .value_name("PATH")
+                .action(ArgAction::Append)
+                .help("YAML/TOML probe template, or a directory of templates; tried before the built-in detectors")
         );
 
     let matches = app.get_matches();
+
+    if let Some(("report", report_matches)) = matches.subcommand() {
+        run_report(report_matches);
+        return;
+    }
     
     let input_file = matches.get_one::<String>("input").unwrap();
-    let timeout: u64 = matches.get_one::<String>("timeout")
-        .unwrap()
-        .parse()
-        .unwrap_or(1);
+    let request_timeout = *matches.get_one::<Duration>("timeout").unwrap();
+    let timeouts = TimeoutConfig {
+        connect: matches.get_one::<Duration>("connect-timeout").copied().unwrap_or(request_timeout),
+        tls_handshake: matches.get_one::<Duration>("tls-timeout").copied().unwrap_or(request_timeout),
+        request: request_timeout,
+    };
         
-    let targets = match CsvParser::parse_from_file(input_file) {
+    let mut mapping = match matches.get_one::<String>("column-map") {
+        Some(path) => match ColumnMapping::from_file(path) {
+            Ok(mapping) => mapping,
+            Err(e) => {
+                error!("Failed to load column mapping: {}", e);
+                process::exit(1);
+            }
+        },
+        None => ColumnMapping::default(),
+    };
+    for (arg, column) in [
+        ("url-column", &mut mapping.url),
+        ("host-column", &mut mapping.host),
+        ("port-column", &mut mapping.port),
+        ("scheme-column", &mut mapping.scheme),
+        ("country-column", &mut mapping.country),
+        ("vhost-column", &mut mapping.vhost),
+    ] {
+        if let Some(name) = matches.get_one::<String>(arg) {
+            *column = Some(name.clone());
+        }
+    }
+
+    let targets = match CsvParser::parse_from_file_with_mapping(input_file, &mapping) {
         Ok(targets) => targets,
         Err(e) => {
             error!("Failed to parse CSV file: {}", e);
@@ -48,7 +466,135 @@ async fn main() {
         }
     };
     
-    let services = match SimpleScanner::scan_services(targets, timeout).await {
+    let unmapped = unmapped_countries(&targets);
+    if !unmapped.is_empty() {
+        let values: Vec<String> = unmapped.iter()
+            .map(|(country, count)| format!("{} ({})", country, count))
+            .collect();
+        println!("Unmapped country values: {}", values.join(", "));
+    }
+
+    let enricher = open_enricher(&matches);
+
+    let mut retry = RetryPolicy {
+        max_attempts: matches.get_one::<u32>("retries").unwrap() + 1,
+        initial_backoff: Duration::from_millis(*matches.get_one::<u64>("retry-backoff").unwrap()),
+        max_backoff: Duration::from_millis(*matches.get_one::<u64>("retry-max-backoff").unwrap()),
+        ..Default::default()
+    };
+    if let Some(kinds) = matches.get_many::<FailureKind>("retry-on") {
+        retry.retry_on = kinds.copied().collect();
+    }
+
+    let adaptive_timeouts = matches.get_flag("adaptive-timeouts").then(|| AdaptiveTimeoutConfig {
+        min: *matches.get_one::<Duration>("adaptive-min").unwrap(),
+        max: *matches.get_one::<Duration>("adaptive-max").unwrap(),
+        ..Default::default()
+    });
+
+    let source = match (matches.get_one::<IpAddr>("source-address"), matches.get_one::<String>("interface")) {
+        (Some(address), _) => Some(SourceAddress { address: *address, interface: None }),
+        (None, Some(interface)) => match SourceAddress::from_interface(interface) {
+            Ok(source) => Some(source),
+            Err(e) => {
+                error!("Failed to resolve interface {}: {}", interface, e);
+                process::exit(1);
+            }
+        },
+        (None, None) => None,
+    };
+    if let Some(source) = &source {
+        println!("Binding outgoing connections to {}", source.address);
+    }
+
+    let credentials = match matches.get_one::<String>("credentials") {
+        Some(path) => CredentialStore::from_file(path),
+        None => CredentialStore::from_env(),
+    };
+    let credentials = match credentials {
+        Ok(credentials) => credentials,
+        Err(e) => {
+            error!("Failed to load credentials: {}", e);
+            process::exit(1);
+        }
+    };
+
+    let mut probe_templates = DetectorRegistry::empty();
+    for path in matches.get_many::<String>("templates").into_iter().flatten() {
+        match TemplateDetector::load(std::path::Path::new(path)) {
+            Ok(templates) => {
+                println!("Loaded {} probe template(s) from {}", templates.len(), path);
+                for template in templates {
+                    probe_templates.register(std::sync::Arc::new(template));
+                }
+            }
+            Err(e) => {
+                error!("Failed to load templates: {}", e);
+                process::exit(1);
+            }
+        }
+    }
+    let names: Vec<String> = matches.get_many::<String>("detectors").unwrap().cloned().collect();
+    let detectors = match DetectorRegistry::select(&names) {
+        Ok(detectors) => detectors,
+        Err(e) => {
+            error!("{}", e);
+            process::exit(1);
+        }
+    };
+
+    let config = ScannerConfig {
+        timeouts,
+        adaptive_timeouts,
+        retry,
+        max_concurrency: matches.get_one::<usize>("concurrency").copied(),
+        rate_limit: matches.get_one::<u32>("rate").copied(),
+        scheme_fallback: matches.get_flag("scheme-fallback"),
+        redirect: RedirectPolicy {
+            max_redirects: *matches.get_one::<usize>("max-redirects").unwrap(),
+            same_host: !matches.get_flag("cross-host-redirects"),
+        },
+        tls: TlsConfig {
+            ca_certs: matches.get_many::<String>("ca-cert").map(|v| v.cloned().collect()).unwrap_or_default(),
+            client_cert: matches.get_one::<String>("client-cert").cloned(),
+            client_key: matches.get_one::<String>("client-key").cloned(),
+            accept_invalid_certs: matches.get_flag("insecure"),
+        },
+        proxy: {
+            let (username, password) = match matches.get_one::<String>("proxy-user") {
+                Some(user) => match user.split_once(':') {
+                    Some((username, password)) => (Some(username.to_string()), Some(password.to_string())),
+                    None => (Some(user.clone()), None),
+                },
+                None => (None, None),
+            };
+            ProxyConfig {
+                url: matches.get_one::<String>("proxy").cloned(),
+                username,
+                password,
+                from_env: !matches.get_flag("no-proxy"),
+            }
+        },
+        source,
+        user_agent: matches.get_one::<String>("user-agent").cloned(),
+        headers: matches.get_many::<(String, String)>("header").map(|v| v.cloned().collect()).unwrap_or_default(),
+        credentials,
+        detectors,
+        templates: probe_templates,
+        probe_loaded_models: matches.get_flag("probe-ps"),
+        model_show_limit: matches.get_flag("enrich-models")
+            .then(|| *matches.get_one::<usize>("show-limit").unwrap()),
+        banner: matches.get_flag("banner-check").then(|| BannerCheck {
+            timeout: *matches.get_one::<Duration>("banner-timeout").unwrap(),
+            concurrency: matches.get_one::<usize>("banner-concurrency").copied(),
+        }),
+    };
+    if config.tls.accept_invalid_certs {
+        eprintln!("WARNING: --insecure is set, TLS certificates will NOT be verified. Results may come from impersonated or intercepted endpoints.");
+    }
+
+    let start_time = chrono::Utc::now();
+    let mut services = match SimpleScanner::scan_services(targets, &config).await {
         Ok(services) => services,
         Err(e) => {
             error!("Failed to discover services: {}", e);
@@ -56,8 +602,35 @@ async fn main() {
         }
     };
     
+    if let Some(enricher) = &enricher {
+        let enriched = enricher.enrich(&mut services);
+        println!("Enriched {} services with GeoIP/ASN data", enriched);
+        let skipped = services.iter()
+            .filter(|s| s.network.as_ref().is_some_and(|n| n.lookup_skipped.is_some()))
+            .count();
+        if skipped > 0 {
+            println!("Skipped GeoIP/ASN lookup for {} hostname targets without a resolved address", skipped);
+        }
+    }
+
+    let summary = SimpleScanner::summarize(&services, &config, start_time, chrono::Utc::now());
+    match SimpleScanner::save_results(&services, &summary) {
+        Ok(filename) => println!("Results saved to {}", filename),
+        Err(e) => error!("Failed to save results: {}", e),
+    }
+
+    for detail in &summary.error_details {
+        println!("  {:<20} {:>8} ({:.1}%)", detail.error_type, detail.count, detail.percentage);
+    }
+
     let active_services_count = services.iter().filter(|s| s.is_active).count();
     
     println!("Scan completed");
     println!("Found {} active services out of {} total services", active_services_count, services.len());
+    if summary.protected_services > 0 {
+        println!("Found {} services protected by authentication", summary.protected_services);
+    }
+    for (product, count) in &summary.other_services {
+        println!("Found {} {} services", count, product);
+    }
 }
@@ -0,0 +1,340 @@
+use chrono::{DateTime, Utc};
+use csv::Reader;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+use std::path::Path;
+use crate::{Result, OllamaError};
+use crate::storage::OllamaService;
+use crate::utils::{GeoIpEnricher, InputSource};
+
+const DEFAULT_REPORT_TEMPLATE: &str = include_str!("../../templates/disclosure_report.md");
+const DEFAULT_ENDPOINT_TEMPLATE: &str = include_str!("../../templates/disclosure_endpoint.md");
+
+/// 网络归属方
+#[derive(Debug, Clone, Default)]
+pub struct Owner {
+    pub asn: Option<u32>,
+    pub organization: Option<String>,
+}
+
+impl Owner {
+    /// 报告分组键，优先使用ASN
+    pub fn key(&self) -> String {
+        match (&self.asn, &self.organization) {
+            (Some(asn), _) => format!("AS{}", asn),
+            (None, Some(org)) => org.clone(),
+            (None, None) => "unknown".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OwnerCsv {
+    network: String,
+    asn: Option<u32>,
+    organization: Option<String>,
+}
+
+/// 本地网段归属表，CSV格式 (network,asn,organization)，network 可以是IP或CIDR
+pub struct OwnerMapping {
+    entries: Vec<(IpAddr, u8, Owner)>,
+}
+
+impl OwnerMapping {
+    pub fn from_csv(path: &str) -> Result<Self> {
+        let mut reader = Reader::from_reader(InputSource::open(path)?);
+        let mut entries = Vec::new();
+
+        for (line_num, result) in reader.deserialize::<OwnerCsv>().enumerate() {
+            let row = result?;
+            let (network, prefix) = Self::parse_network(&row.network).ok_or_else(|| {
+                OllamaError::ParseError(format!("Invalid network '{}' at line {}", row.network, line_num + 1))
+            })?;
+            entries.push((network, prefix, Owner {
+                asn: row.asn,
+                organization: row.organization.filter(|o| !o.is_empty()),
+            }));
+        }
+
+        Ok(Self { entries })
+    }
+
+    fn parse_network(value: &str) -> Option<(IpAddr, u8)> {
+        let (addr, prefix) = match value.trim().split_once('/') {
+            Some((addr, prefix)) => (addr.parse::<IpAddr>().ok()?, Some(prefix.parse::<u8>().ok()?)),
+            None => (value.trim().parse::<IpAddr>().ok()?, None),
+        };
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(max);
+        (prefix <= max).then_some((addr, prefix))
+    }
+
+    fn contains(network: IpAddr, prefix: u8, ip: IpAddr) -> bool {
+        match (network, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+
+    /// 最长前缀匹配
+    pub fn lookup(&self, ip: IpAddr) -> Option<&Owner> {
+        self.entries.iter()
+            .filter(|(network, prefix, _)| Self::contains(*network, *prefix, ip))
+            .max_by_key(|(_, prefix, _)| *prefix)
+            .map(|(_, _, owner)| owner)
+    }
+}
+
+/// 可编辑的报告模板，占位符格式为 `{{name}}`
+pub struct DisclosureTemplates {
+    pub report: String,
+    pub endpoint: String,
+}
+
+impl Default for DisclosureTemplates {
+    fn default() -> Self {
+        Self {
+            report: DEFAULT_REPORT_TEMPLATE.to_string(),
+            endpoint: DEFAULT_ENDPOINT_TEMPLATE.to_string(),
+        }
+    }
+}
+
+impl DisclosureTemplates {
+    /// 从目录加载 `disclosure_report.md` 和 `disclosure_endpoint.md`，缺失的文件使用内置模板
+    pub fn from_dir(dir: &str) -> Result<Self> {
+        let load = |name: &str, default: &str| -> Result<String> {
+            let path = Path::new(dir).join(name);
+            if path.exists() {
+                Ok(std::fs::read_to_string(path)?)
+            } else {
+                Ok(default.to_string())
+            }
+        };
+
+        Ok(Self {
+            report: load("disclosure_report.md", DEFAULT_REPORT_TEMPLATE)?,
+            endpoint: load("disclosure_endpoint.md", DEFAULT_ENDPOINT_TEMPLATE)?,
+        })
+    }
+
+    /// 一遍替换全部占位符，替换进来的内容不会再被展开；未知的占位符原样保留
+    fn render(template: &str, values: &[(&str, String)]) -> String {
+        let mut output = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find("{{") {
+            output.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            let value = after.find("}}").and_then(|end| {
+                let name = after[..end].trim();
+                values.iter().find(|(key, _)| *key == name).map(|(_, value)| (value, end))
+            });
+            match value {
+                Some((value, end)) => {
+                    output.push_str(value);
+                    rest = &after[end + 2..];
+                }
+                None => {
+                    output.push_str("{{");
+                    rest = after;
+                }
+            }
+        }
+        output.push_str(rest);
+        output
+    }
+
+    /// 扫描到的内容（模型名、组织名等）写入 Markdown 前转义，避免破坏表格或插入 HTML
+    fn escape(value: &str) -> String {
+        value.chars().fold(String::with_capacity(value.len()), |mut escaped, c| {
+            match c {
+                '\\' | '|' | '`' | '*' | '_' | '[' | ']' => {
+                    escaped.push('\\');
+                    escaped.push(c);
+                }
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '\r' | '\n' => escaped.push(' '),
+                c => escaped.push(c),
+            }
+            escaped
+        })
+    }
+}
+
+/// 同一端点在多次扫描中的汇总
+#[derive(Debug, Clone)]
+pub struct ExposedEndpoint {
+    pub service: OllamaService,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+pub struct DisclosureReporter {
+    pub owners: Option<OwnerMapping>,
+    pub enricher: Option<GeoIpEnricher>,
+    pub templates: DisclosureTemplates,
+}
+
+/// save_results 写出的 `ollama_scan_*_summary.json`（可能已被压缩）
+fn is_summary_file(path: &str) -> bool {
+    ["_summary.json", "_summary.json.gz", "_summary.json.zst"].iter().any(|suffix| path.ends_with(suffix))
+}
+
+impl DisclosureReporter {
+    /// 读取一个或多个扫描结果文件
+    pub fn load_results<P: AsRef<str>>(paths: &[P]) -> Result<Vec<OllamaService>> {
+        let mut services = Vec::new();
+        for path in paths {
+            // 与结果文件同目录的扫描汇总不是服务列表，`results/*.json` 会把它们一起匹配进来
+            if is_summary_file(path.as_ref()) {
+                log::info!("Skipping scan summary {}", path.as_ref());
+                continue;
+            }
+            let reader = InputSource::open(path.as_ref())?;
+            let mut batch: Vec<OllamaService> = serde_json::from_reader(reader)?;
+            services.append(&mut batch);
+        }
+        Ok(services)
+    }
+
+    /// 确定服务的归属方：本地归属表优先，其次是结果中或现场查询的 GeoIP 数据
+    fn owner_of(&self, service: &OllamaService) -> Owner {
+        let ip = service.resolved_ip();
+
+        if let Some(owner) = ip.zip(self.owners.as_ref()).and_then(|(ip, owners)| owners.lookup(ip)) {
+            return owner.clone();
+        }
+
+        let network = service.network.clone()
+            .filter(|network| network.lookup_skipped.is_none())
+            .or_else(|| ip.zip(self.enricher.as_ref()).and_then(|(ip, enricher)| enricher.lookup(ip)));
+        match network {
+            Some(network) => Owner {
+                asn: network.asn,
+                organization: network.organization,
+            },
+            None => Owner::default(),
+        }
+    }
+
+    /// 按归属方分组暴露的端点，同一端点的多次结果合并为首次/最近发现时间
+    pub fn group_by_owner(&self, services: Vec<OllamaService>) -> BTreeMap<String, (Owner, Vec<ExposedEndpoint>)> {
+        let mut endpoints: HashMap<String, ExposedEndpoint> = HashMap::new();
+        for service in services.into_iter().filter(|s| s.is_active) {
+            let scan_time = service.scan_time;
+            endpoints.entry(service.target.base_url())
+                .and_modify(|existing| {
+                    existing.first_seen = existing.first_seen.min(scan_time);
+                    if scan_time > existing.last_seen {
+                        existing.last_seen = scan_time;
+                        existing.service = service.clone();
+                    }
+                })
+                .or_insert(ExposedEndpoint {
+                    service,
+                    first_seen: scan_time,
+                    last_seen: scan_time,
+                });
+        }
+
+        let mut groups: BTreeMap<String, (Owner, Vec<ExposedEndpoint>)> = BTreeMap::new();
+        for endpoint in endpoints.into_values() {
+            let owner = self.owner_of(&endpoint.service);
+            groups.entry(owner.key())
+                .or_insert_with(|| (owner, Vec::new()))
+                .1
+                .push(endpoint);
+        }
+        for (_, endpoints) in groups.values_mut() {
+            endpoints.sort_by_key(|endpoint| endpoint.service.target.base_url());
+        }
+        groups
+    }
+
+    fn render_report(&self, owner: &Owner, endpoints: &[ExposedEndpoint]) -> String {
+        let rows: String = endpoints.iter()
+            .map(|endpoint| DisclosureTemplates::render(&self.templates.endpoint, &[
+                ("endpoint", DisclosureTemplates::escape(&endpoint.service.target.base_url())),
+                ("version", DisclosureTemplates::escape(endpoint.service.version.as_deref().unwrap_or("unknown"))),
+                ("model_count", endpoint.service.models.len().to_string()),
+                ("models", DisclosureTemplates::escape(&endpoint.service.models.iter().map(|m| m.name.as_str()).collect::<Vec<_>>().join(", "))),
+                ("first_seen", endpoint.first_seen.to_rfc3339()),
+                ("last_seen", endpoint.last_seen.to_rfc3339()),
+            ]))
+            .collect();
+
+        DisclosureTemplates::render(&self.templates.report, &[
+            ("owner", DisclosureTemplates::escape(&owner.key())),
+            ("asn", owner.asn.map(|asn| format!("AS{}", asn)).unwrap_or_else(|| "unknown ASN".to_string())),
+            ("organization", DisclosureTemplates::escape(owner.organization.as_deref().unwrap_or("unknown organization"))),
+            ("endpoint_count", endpoints.len().to_string()),
+            ("generated_at", Utc::now().to_rfc3339()),
+            // 已由端点模板渲染并转义
+            ("endpoints", rows.trim_end().to_string()),
+        ])
+    }
+
+    /// 为每个归属方写入一个报告文件，返回写入的文件路径
+    pub fn write_reports(&self, services: Vec<OllamaService>, output_dir: &str) -> Result<Vec<String>> {
+        std::fs::create_dir_all(output_dir)?;
+
+        let mut written = Vec::new();
+        for (key, (owner, endpoints)) in self.group_by_owner(services) {
+            let name = match &owner.organization {
+                Some(org) if owner.asn.is_some() => format!("{}_{}", key, org),
+                _ => key,
+            };
+            let filename: String = name.chars()
+                .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' })
+                .collect();
+            let path = Path::new(output_dir).join(format!("{}.md", filename));
+
+            std::fs::write(&path, self.render_report(&owner, &endpoints))?;
+            written.push(path.to_string_lossy().into_owned());
+        }
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_does_not_expand_substituted_values() {
+        let rendered = DisclosureTemplates::render("{{a}} {{ b }} {{c}}", &[
+            ("a", "{{b}}".to_string()),
+            ("b", "x".to_string()),
+        ]);
+        assert_eq!(rendered, "{{b}} x {{c}}");
+    }
+
+    #[test]
+    fn escape_keeps_table_cells_intact() {
+        assert_eq!(DisclosureTemplates::escape("a|b\nc <img>"), "a\\|b c &lt;img&gt;");
+    }
+
+    #[test]
+    fn load_results_skips_scan_summaries() {
+        let dir = std::env::temp_dir().join(format!("free-ollama-report-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let results = dir.join("ollama_scan_20250101_000000.json");
+        let summary = dir.join("ollama_scan_20250101_000000_summary.json");
+        std::fs::write(&results, serde_json::to_string(&[OllamaService::default()]).unwrap()).unwrap();
+        std::fs::write(&summary, r#"{"total_targets": 1}"#).unwrap();
+
+        let paths = [results.to_string_lossy().into_owned(), summary.to_string_lossy().into_owned()];
+        let services = DisclosureReporter::load_results(&paths);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(services.unwrap().len(), 1);
+    }
+}
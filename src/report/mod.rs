@@ -0,0 +1,3 @@
+pub mod disclosure;
+
+pub use disclosure::*;
\ No newline at end of file
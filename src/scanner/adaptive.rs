@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::Duration;
+use crate::{Result, OllamaError};
+
+/// 根据观测到的建连耗时动态调整超时
+#[derive(Debug, Clone)]
+pub struct AdaptiveTimeoutConfig {
+    pub min: Duration,
+    pub max: Duration,
+    /// 超时 = (平滑RTT + 4 * RTT偏差) * factor
+    pub factor: f64,
+    /// 网段样本数达到该值后才启用自适应超时
+    pub min_samples: u32,
+}
+
+impl Default for AdaptiveTimeoutConfig {
+    fn default() -> Self {
+        Self {
+            min: Duration::from_millis(200),
+            max: Duration::from_secs(5),
+            factor: 3.0,
+            min_samples: 3,
+        }
+    }
+}
+
+impl AdaptiveTimeoutConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.min.is_zero() {
+            return Err(OllamaError::InvalidInput("adaptive timeout minimum must be greater than zero".to_string()));
+        }
+        if self.min > self.max {
+            return Err(OllamaError::InvalidInput(format!(
+                "adaptive timeout minimum {:?} is greater than maximum {:?}", self.min, self.max
+            )));
+        }
+        if !(self.factor.is_finite() && self.factor > 0.0) {
+            return Err(OllamaError::InvalidInput(format!("adaptive timeout factor must be positive, got {}", self.factor)));
+        }
+        Ok(())
+    }
+}
+
+/// 不可达网段中每隔多少次连接仍用配置的超时试探一次，避免慢速网段被误判后一直用下限
+const DEAD_NETWORK_PROBE_INTERVAL: u32 = 10;
+
+/// 类似 TCP RTO 的 RTT 估计
+#[derive(Debug, Clone, Copy, Default)]
+struct RttStats {
+    srtt: f64,
+    rttvar: f64,
+    samples: u32,
+    /// 建连超时次数
+    timeouts: u32,
+    /// 按不可达网段处理的连接次数
+    dead_attempts: u32,
+}
+
+impl RttStats {
+    fn update(&mut self, rtt: f64) {
+        if self.samples == 0 {
+            self.srtt = rtt;
+            self.rttvar = rtt / 2.0;
+        } else {
+            self.rttvar = 0.75 * self.rttvar + 0.25 * (self.srtt - rtt).abs();
+            self.srtt = 0.875 * self.srtt + 0.125 * rtt;
+        }
+        self.samples += 1;
+    }
+}
+
+/// 按网段（IPv4 /16，IPv6 /48）统计的建连 RTT 和建连超时
+pub struct AdaptiveTimeouts {
+    config: AdaptiveTimeoutConfig,
+    stats: Mutex<HashMap<String, RttStats>>,
+}
+
+impl AdaptiveTimeouts {
+    pub fn new(config: AdaptiveTimeoutConfig) -> Result<Self> {
+        config.validate()?;
+        Ok(Self {
+            config,
+            stats: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// 地址所属的网段
+    pub fn network_key(ip: IpAddr) -> String {
+        match ip {
+            IpAddr::V4(ip) => {
+                let [a, b, _, _] = ip.octets();
+                format!("{}.{}.0.0/16", a, b)
+            }
+            IpAddr::V6(ip) => {
+                let segments = ip.segments();
+                format!("{:x}:{:x}:{:x}::/48", segments[0], segments[1], segments[2])
+            }
+        }
+    }
+
+    /// 网段的建连超时和 TLS 握手超时，没有足够依据时返回 None
+    ///
+    /// RTT 样本足够时按估计值计算（握手按两个往返）；只有超时、没有任何 RTT 样本的网段
+    /// 多半不可达，使用下限快速失败，但每隔若干次仍按配置的超时试探一次。
+    pub fn timeouts_for(&self, network: &str) -> Option<(Duration, Duration)> {
+        let mut stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        let rtt = stats.get_mut(network)?;
+        if rtt.samples >= self.config.min_samples {
+            let timeout = |rtts: f64| {
+                let secs = (rtt.srtt + 4.0 * rtt.rttvar) * self.config.factor * rtts;
+                Duration::from_secs_f64(secs).clamp(self.config.min, self.config.max)
+            };
+            return Some((timeout(1.0), timeout(2.0)));
+        }
+        if rtt.samples == 0 && rtt.timeouts >= self.config.min_samples {
+            rtt.dead_attempts += 1;
+            if rtt.dead_attempts % DEAD_NETWORK_PROBE_INTERVAL != 0 {
+                return Some((self.config.min, (self.config.min * 2).min(self.config.max)));
+            }
+        }
+        None
+    }
+
+    /// 记录一次完成的往返：建连成功或被拒绝
+    pub fn record(&self, network: &str, rtt: Duration) {
+        let mut stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        stats.entry(network.to_string()).or_default().update(rtt.as_secs_f64());
+    }
+
+    /// 记录一次建连超时
+    pub fn record_timeout(&self, network: &str) {
+        let mut stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        stats.entry(network.to_string()).or_default().timeouts += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dead_network_uses_minimum_and_still_probes() {
+        let adaptive = AdaptiveTimeouts::new(AdaptiveTimeoutConfig::default()).unwrap();
+        let network = "10.1.0.0/16";
+        for _ in 0..3 {
+            assert_eq!(adaptive.timeouts_for(network), None);
+            adaptive.record_timeout(network);
+        }
+
+        let attempts: Vec<_> = (0..DEAD_NETWORK_PROBE_INTERVAL).map(|_| adaptive.timeouts_for(network)).collect();
+        assert!(attempts[..9].iter().all(|t| *t == Some((Duration::from_millis(200), Duration::from_millis(400)))));
+        assert_eq!(attempts[9], None);
+    }
+
+    #[test]
+    fn rtt_samples_take_precedence_over_timeouts() {
+        let adaptive = AdaptiveTimeouts::new(AdaptiveTimeoutConfig::default()).unwrap();
+        let network = "10.2.0.0/16";
+        adaptive.record_timeout(network);
+        for _ in 0..3 {
+            adaptive.record(network, Duration::from_millis(100));
+        }
+        let (connect, tls) = adaptive.timeouts_for(network).unwrap();
+        assert!(connect > Duration::from_millis(200) && tls > connect);
+    }
+
+    #[test]
+    fn minimum_above_maximum_is_rejected() {
+        let config = AdaptiveTimeoutConfig {
+            min: Duration::from_secs(10),
+            max: Duration::from_secs(5),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+        assert!(AdaptiveTimeouts::new(config).is_err());
+
+        let equal = AdaptiveTimeoutConfig {
+            min: Duration::from_secs(5),
+            max: Duration::from_secs(5),
+            ..Default::default()
+        };
+        assert!(AdaptiveTimeouts::new(equal).is_ok());
+    }
+}
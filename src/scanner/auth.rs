@@ -0,0 +1,65 @@
+use reqwest::header::{HeaderMap, PROXY_AUTHENTICATE, WWW_AUTHENTICATE};
+use crate::storage::AuthProtection;
+
+/// 常见认证代理的特征：(产品名, 响应头名或 Set-Cookie/跳转地址中出现的关键字)
+const PROXY_SIGNATURES: &[(&str, &[&str])] = &[
+    ("oauth2-proxy", &["_oauth2_proxy", "/oauth2/start", "/oauth2/sign_in", "x-auth-request-redirect"]),
+    ("Cloudflare Access", &["cloudflareaccess.com", "cf_authorization", "cf-access-domain"]),
+    ("Authelia", &["authelia"]),
+    ("Authentik", &["goauthentik", "authentik_proxy"]),
+    ("Pomerium", &["pomerium", "_pomerium"]),
+    ("Vouch Proxy", &["vouchcookie", "/vouch/"]),
+    ("Google IAP", &["gcp_iap", "__host-gcp_iap"]),
+];
+
+/// 在响应头和跳转地址中查找认证代理特征
+pub fn proxy_signature(headers: &HeaderMap, url: &str) -> Option<&'static str> {
+    let mut haystack = url.to_lowercase();
+    for (name, value) in headers {
+        haystack.push('\n');
+        haystack.push_str(name.as_str());
+        haystack.push(':');
+        haystack.push_str(&String::from_utf8_lossy(value.as_bytes()).to_lowercase());
+    }
+
+    PROXY_SIGNATURES.iter()
+        .find(|(_, needles)| needles.iter().any(|needle| haystack.contains(needle)))
+        .map(|(product, _)| *product)
+}
+
+/// 拒绝访问的状态码
+pub fn is_denied(status: u16) -> bool {
+    matches!(status, 401 | 403 | 407)
+}
+
+/// 由最终响应判断服务是否受认证保护
+///
+/// 认证质询头或认证代理特征出现即视为受保护；单独的 401/403/407 很常见
+/// （WAF、默认拒绝的反向代理），只有调用方另有 Ollama 证据（如根路径横幅）时才算。
+pub fn detect_protection(status: u16, headers: &HeaderMap, signature: Option<&str>, ollama_evidence: bool) -> Option<AuthProtection> {
+    let challenge = headers.get(WWW_AUTHENTICATE)
+        .or_else(|| headers.get(PROXY_AUTHENTICATE))
+        .and_then(|value| value.to_str().ok());
+
+    if challenge.is_none() && signature.is_none() && !(ollama_evidence && is_denied(status)) {
+        return None;
+    }
+
+    Some(AuthProtection {
+        status_code: status,
+        scheme: challenge.and_then(|c| c.split_whitespace().next()).map(str::to_string),
+        realm: challenge.and_then(realm),
+        proxy: signature.map(str::to_string),
+    })
+}
+
+/// 提取质询中的 realm 参数
+fn realm(challenge: &str) -> Option<String> {
+    let start = challenge.to_lowercase().find("realm=")? + "realm=".len();
+    let value = &challenge[start..];
+    let value = match value.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next()?,
+        None => value.split([',', ' ']).next()?,
+    };
+    Some(value.to_string())
+}
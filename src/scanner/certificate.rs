@@ -0,0 +1,40 @@
+use chrono::{DateTime, Utc};
+use std::net::IpAddr;
+use x509_parser::prelude::{FromDer, GeneralName, X509Certificate};
+use crate::storage::CertificateInfo;
+
+/// 解析 DER 编码的服务端证书，无法解析时返回 None
+pub fn parse_certificate(der: &[u8]) -> Option<CertificateInfo> {
+    let (_, cert) = X509Certificate::from_der(der).ok()?;
+
+    let sans = cert.subject_alternative_name().ok().flatten()
+        .map(|san| san.value.general_names.iter().filter_map(general_name).collect())
+        .unwrap_or_default();
+    let not_before = DateTime::from_timestamp(cert.validity().not_before.timestamp(), 0)?;
+    let not_after = DateTime::from_timestamp(cert.validity().not_after.timestamp(), 0)?;
+
+    Some(CertificateInfo {
+        subject: cert.subject().to_string(),
+        issuer: cert.issuer().to_string(),
+        sans,
+        not_before,
+        not_after,
+        // 签发者与主体相同即视为自签名，不校验签名本身
+        self_signed: cert.subject().as_raw() == cert.issuer().as_raw(),
+        expired: not_after < Utc::now(),
+    })
+}
+
+fn general_name(name: &GeneralName) -> Option<String> {
+    match name {
+        GeneralName::DNSName(name) => Some(name.to_string()),
+        GeneralName::RFC822Name(email) => Some(email.to_string()),
+        GeneralName::URI(uri) => Some(uri.to_string()),
+        GeneralName::IPAddress(bytes) => match bytes.len() {
+            4 => Some(IpAddr::from(<[u8; 4]>::try_from(*bytes).ok()?).to_string()),
+            16 => Some(IpAddr::from(<[u8; 16]>::try_from(*bytes).ok()?).to_string()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
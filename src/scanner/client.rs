@@ -0,0 +1,258 @@
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, HOST, USER_AGENT};
+use reqwest::redirect::Policy;
+use reqwest::{Certificate, Client, ClientBuilder, Identity, Method, RequestBuilder, Url};
+use std::net::SocketAddr;
+use tokio::net::lookup_host;
+use crate::{Result, OllamaError};
+use crate::scanner::{ConnectionProber, Credential, ProxyRoutes, ScannerConfig, ScopedCredential};
+use crate::storage::Target;
+
+/// 按扫描配置构建HTTP客户端
+///
+/// 证书只在创建时加载一次；带虚拟主机名的目标需要把该名称解析到目标地址，
+/// 每个目标各自构建一个客户端，其余目标共用同一个客户端。
+pub struct ClientFactory {
+    settings: ClientSettings,
+    shared: Client,
+    /// 经预连接发送请求时需要自行附加的默认请求头
+    default_headers: HeaderMap,
+}
+
+/// 预先加载好的客户端设置
+struct ClientSettings {
+    config: ScannerConfig,
+    proxy: Option<ProxyRoutes>,
+    headers: HeaderMap,
+    ca_certs: Vec<Certificate>,
+    identity: Option<Identity>,
+}
+
+/// 单个目标的请求方式
+pub struct RequestPlan {
+    pub client: Client,
+    /// 请求使用的基础URL，虚拟主机目标使用虚拟主机名
+    pub base_url: String,
+    /// 无法改写域名解析时（经代理访问）显式设置的 Host 头
+    pub host_header: Option<String>,
+    /// 匹配到的凭据
+    pub credential: Option<ScopedCredential>,
+    default_headers: HeaderMap,
+}
+
+impl RequestPlan {
+    pub fn get(&self, url: &str) -> RequestBuilder {
+        self.request(Method::GET, url)
+    }
+
+    pub fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        let mut request = self.client.request(method, url);
+        // 虚拟主机名只用于目标本身，跳转到其他主机时使用URL中的主机
+        let same_host = Url::parse(url).ok().zip(Url::parse(&self.base_url).ok())
+            .is_some_and(|(url, base)| url.host_str() == base.host_str());
+        if let Some(host) = self.host_header.as_ref().filter(|_| same_host) {
+            request = request.header(HOST, host);
+        }
+        let Some(scoped) = self.credential.as_ref().filter(|scoped| self.sends_credential(scoped, url)) else {
+            return request;
+        };
+        match &scoped.credential {
+            Credential::Bearer(token) => request.bearer_auth(token),
+            Credential::Basic { username, password } => request.basic_auth(username, Some(password)),
+        }
+    }
+
+    pub fn is_https(&self) -> bool {
+        self.base_url.starts_with("https://")
+    }
+
+    /// 经预连接发送的完整请求，补上 reqwest 只在自己发送时附加的默认请求头
+    pub fn build(&self, method: Method, url: &str) -> reqwest::Result<reqwest::Request> {
+        let mut request = self.request(method, url).build()?;
+        for name in self.default_headers.keys() {
+            if !request.headers().contains_key(name) {
+                for value in self.default_headers.get_all(name) {
+                    request.headers_mut().append(name, value.clone());
+                }
+            }
+        }
+        Ok(request)
+    }
+
+    /// 凭据只发给目标本身：协议、主机和端口都与基础URL一致，
+    /// 且除非范围明确允许，只经 https 发送
+    fn sends_credential(&self, scoped: &ScopedCredential, url: &str) -> bool {
+        let (Ok(url), Ok(base)) = (Url::parse(url), Url::parse(&self.base_url)) else {
+            return false;
+        };
+        url.scheme() == base.scheme()
+            && url.host_str() == base.host_str()
+            && url.port_or_known_default() == base.port_or_known_default()
+            && (url.scheme() == "https" || scoped.allow_http)
+    }
+}
+
+impl ClientFactory {
+    pub fn new(config: &ScannerConfig, proxy: Option<ProxyRoutes>) -> Result<Self> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &config.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| OllamaError::InvalidInput(format!("Invalid header name '{}': {}", name, e)))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|e| OllamaError::InvalidInput(format!("Invalid value for header '{}': {}", name, e)))?;
+            headers.append(name, value);
+        }
+
+        let mut ca_certs = Vec::new();
+        for path in &config.tls.ca_certs {
+            let certs = Certificate::from_pem_bundle(&std::fs::read(path)?)?;
+            if certs.is_empty() {
+                return Err(OllamaError::InvalidInput(format!("No certificates found in {}", path)));
+            }
+            log::info!("Loaded {} CA certificate(s) from {}", certs.len(), path);
+            ca_certs.extend(certs);
+        }
+        let identity = config.tls.client_identity()?
+            .map(|(cert, key)| Identity::from_pkcs8_pem(&cert, &key))
+            .transpose()?;
+        if config.tls.accept_invalid_certs {
+            log::warn!("TLS certificate verification is DISABLED: invalid, expired and self-signed certificates will be accepted");
+        }
+
+        let settings = ClientSettings {
+            config: config.clone(),
+            proxy,
+            headers,
+            ca_certs,
+            identity,
+        };
+        let shared = settings.builder().build()?;
+
+        let mut default_headers = settings.headers.clone();
+        let user_agent = HeaderValue::from_str(config.user_agent())
+            .map_err(|e| OllamaError::InvalidInput(format!("Invalid User-Agent: {}", e)))?;
+        default_headers.entry(USER_AGENT).or_insert(user_agent);
+        default_headers.entry(ACCEPT).or_insert(HeaderValue::from_static("*/*"));
+        Ok(Self { settings, shared, default_headers })
+    }
+
+    /// 确定目标的请求方式
+    ///
+    /// 直连时把虚拟主机名解析到目标地址，Host 头和 SNI 都使用虚拟主机名；
+    /// 经代理访问时域名由代理解析，只能改写 Host 头，SNI 仍是目标主机。
+    pub async fn plan(&self, prober: &ConnectionProber, target: &Target) -> RequestPlan {
+        let shared = RequestPlan {
+            client: self.shared.clone(),
+            base_url: target.base_url(),
+            host_header: None,
+            credential: self.settings.config.credentials.credential_for(target).cloned(),
+            default_headers: self.default_headers.clone(),
+        };
+        let Some(vhost) = &target.vhost else {
+            return shared;
+        };
+        if prober.proxy_egress(target).is_some() {
+            return RequestPlan {
+                host_header: Some(target.host_header()),
+                ..shared
+            };
+        }
+
+        let addrs: Vec<SocketAddr> = match target.ip_addr() {
+            Some(ip) => vec![SocketAddr::new(ip, target.port)],
+            // 解析失败时交给预连接报告 DNS 错误
+            None => match lookup_host((target.host.as_str(), target.port)).await {
+                Ok(addrs) => addrs.collect(),
+                Err(_) => return shared,
+            },
+        };
+        match self.settings.builder().resolve_to_addrs(vhost, &addrs).build() {
+            Ok(client) => RequestPlan {
+                client,
+                host_header: None,
+                base_url: format!("{}://{}:{}", if target.is_https { "https" } else { "http" }, vhost, target.port),
+                ..shared
+            },
+            Err(e) => {
+                log::warn!("Failed to build client for vhost {}: {}", vhost, e);
+                RequestPlan {
+                    host_header: Some(target.host_header()),
+                    ..shared
+                }
+            }
+        }
+    }
+}
+
+impl ClientSettings {
+    /// 按超时、重定向、请求头、代理、源地址和 TLS 设置构建
+    fn builder(&self) -> ClientBuilder {
+        let config = &self.config;
+        let mut builder = Client::builder()
+            .connect_timeout(config.timeouts.connect + config.timeouts.tls_handshake)
+            .timeout(config.timeouts.request)
+            .redirect(Policy::none())
+            .user_agent(config.user_agent())
+            .default_headers(self.headers.clone());
+
+        // 环境变量中的代理已经由 ProxyConfig 解析，不再让 reqwest 自行读取
+        builder = match &self.proxy {
+            Some(routes) => builder.proxy(routes.to_proxy()),
+            None => builder.no_proxy(),
+        };
+        if let Some(source) = &config.source {
+            builder = builder.local_address(source.address);
+        }
+
+        for cert in &self.ca_certs {
+            builder = builder.add_root_certificate(cert.clone());
+        }
+        if let Some(identity) = &self.identity {
+            builder = builder.identity(identity.clone());
+        }
+        if config.tls.accept_invalid_certs {
+            builder = builder
+                .danger_accept_invalid_certs(true)
+                .danger_accept_invalid_hostnames(true);
+        }
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::AUTHORIZATION;
+
+    fn plan(base_url: &str, allow_http: bool) -> RequestPlan {
+        let credential = ScopedCredential::new("gw.example.com", Credential::Bearer("token".to_string()), allow_http).unwrap();
+        RequestPlan {
+            client: Client::new(),
+            base_url: base_url.to_string(),
+            host_header: None,
+            credential: Some(credential),
+            default_headers: HeaderMap::new(),
+        }
+    }
+
+    fn sends(plan: &RequestPlan, url: &str) -> bool {
+        plan.get(url).build().unwrap().headers().contains_key(AUTHORIZATION)
+    }
+
+    #[test]
+    fn credentials_go_only_to_the_planned_origin() {
+        let https = plan("https://gw.example.com:8443", false);
+        assert!(sends(&https, "https://gw.example.com:8443/api/tags"));
+        assert!(!sends(&https, "https://gw.example.com/api/tags"));
+        assert!(!sends(&https, "https://gw.example.com:9443/api/tags"));
+        assert!(!sends(&https, "http://gw.example.com:8443/api/tags"));
+        assert!(!sends(&https, "https://other.example.com:8443/api/tags"));
+    }
+
+    #[test]
+    fn credentials_need_allow_http_for_plain_http() {
+        assert!(!sends(&plan("http://gw.example.com:11434", false), "http://gw.example.com:11434/api/tags"));
+        let allowed = plan("http://gw.example.com:11434", true);
+        assert!(sends(&allowed, "http://gw.example.com:11434/api/tags"));
+        assert!(!sends(&allowed, "https://gw.example.com:11434/api/tags"));
+    }
+}
@@ -0,0 +1,307 @@
+use std::net::IpAddr;
+use std::time::Duration;
+use crate::{Result, OllamaError};
+use crate::scanner::{AdaptiveTimeoutConfig, CredentialStore, DetectorRegistry, ProxyConfig};
+use crate::storage::{FailureKind, ScanMetadata};
+
+/// 失败重试策略，指数退避并带随机抖动
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// 总尝试次数，1 表示不重试
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: f64,
+    /// 抖动比例 (0.0-1.0)，实际等待时间在 [backoff * (1 - jitter), backoff] 之间
+    pub jitter: f64,
+    /// 允许重试的失败类型
+    pub retry_on: Vec<FailureKind>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+            multiplier: 2.0,
+            jitter: 0.5,
+            retry_on: vec![
+                FailureKind::ConnectTimeout,
+                FailureKind::ReadTimeout,
+                FailureKind::ConnectionReset,
+            ],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 第 `attempt` 次尝试失败后是否继续重试
+    pub fn should_retry(&self, kind: FailureKind, attempt: u32) -> bool {
+        attempt < self.max_attempts && self.retry_on.contains(&kind)
+    }
+
+    /// 第 `attempt` 次尝试失败后的等待时间
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1) as i32;
+        let base = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        let capped = base.min(self.max_backoff.as_secs_f64());
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        Duration::from_secs_f64(capped * (1.0 - jitter * fastrand::f64()))
+    }
+}
+
+/// 时长参数的上限
+const MAX_DURATION: Duration = Duration::from_secs(3600);
+
+/// 解析时长，支持 `500ms`、`2s`、`1.5`（无单位时按秒计算），只接受十进制小数
+pub fn parse_duration(value: &str) -> std::result::Result<Duration, String> {
+    let value = value.trim();
+    let (number, scale) = if let Some(ms) = value.strip_suffix("ms") {
+        (ms, 0.001)
+    } else if let Some(secs) = value.strip_suffix('s') {
+        (secs, 1.0)
+    } else {
+        (value, 1.0)
+    };
+
+    // f64 的解析还接受 1e9、inf、NaN 等写法，先限定为数字和至多一个小数点
+    let number = number.trim();
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+    let decimal = !whole.is_empty()
+        && whole.chars().all(|c| c.is_ascii_digit())
+        && fraction.chars().all(|c| c.is_ascii_digit());
+    let secs = decimal.then(|| number.parse::<f64>().ok()).flatten()
+        .ok_or_else(|| format!("invalid duration '{}', expected e.g. 500ms, 2s or 1.5", value))?
+        * scale;
+    if secs < 0.001 {
+        return Err(format!("duration '{}' must be at least 1ms", value));
+    }
+    if secs > MAX_DURATION.as_secs_f64() {
+        return Err(format!("duration '{}' must be at most {}s", value, MAX_DURATION.as_secs()));
+    }
+    Ok(Duration::from_millis((secs * 1000.0).round() as u64))
+}
+
+/// 连接、TLS 握手和整个请求的超时
+#[derive(Debug, Clone)]
+pub struct TimeoutConfig {
+    /// DNS 解析加 TCP 建连
+    pub connect: Duration,
+    /// HTTPS 目标的 TLS 握手
+    pub tls_handshake: Duration,
+    /// 发送请求到读完响应体
+    pub request: Duration,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            connect: Duration::from_secs(3),
+            tls_handshake: Duration::from_secs(3),
+            request: Duration::from_secs(3),
+        }
+    }
+}
+
+impl TimeoutConfig {
+    pub fn validate(&self) -> Result<()> {
+        for (name, value) in [
+            ("connect", self.connect),
+            ("TLS handshake", self.tls_handshake),
+            ("request", self.request),
+        ] {
+            if value.is_zero() {
+                return Err(OllamaError::InvalidInput(format!("{} timeout must be greater than zero", name)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 重定向策略，重定向由扫描器逐跳跟随以便记录跳转链
+#[derive(Debug, Clone)]
+pub struct RedirectPolicy {
+    /// 最多跟随的跳转次数，0 表示不跟随
+    pub max_redirects: usize,
+    /// 只跟随指向同一主机的跳转（协议和端口可以不同）
+    pub same_host: bool,
+}
+
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        Self {
+            max_redirects: 10,
+            same_host: true,
+        }
+    }
+}
+
+/// 两阶段扫描的第一阶段：只请求根路径确认 Ollama 横幅，通过的目标再做完整探测
+#[derive(Debug, Clone)]
+pub struct BannerCheck {
+    /// 横幅请求的超时，覆盖建连和读取
+    pub timeout: Duration,
+    /// 第一阶段同时进行的请求数上限，None 表示不限制；第二阶段使用 max_concurrency
+    pub concurrency: Option<usize>,
+}
+
+impl Default for BannerCheck {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(1),
+            concurrency: None,
+        }
+    }
+}
+
+/// TLS 设置：私有CA、双向 TLS 客户端证书及不校验证书模式
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfig {
+    /// 额外信任的根证书文件 (PEM，每个文件可包含多张证书)
+    pub ca_certs: Vec<String>,
+    /// 客户端证书文件 (PEM)，需与 `client_key` 同时设置
+    pub client_cert: Option<String>,
+    /// 客户端私钥文件 (PKCS#8 PEM)
+    pub client_key: Option<String>,
+    /// 不校验服务端证书和主机名
+    pub accept_invalid_certs: bool,
+}
+
+impl TlsConfig {
+    /// 读取客户端证书和私钥，未配置时返回 None
+    pub fn client_identity(&self) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
+        match (&self.client_cert, &self.client_key) {
+            (Some(cert), Some(key)) => Ok(Some((std::fs::read(cert)?, std::fs::read(key)?))),
+            (None, None) => Ok(None),
+            _ => Err(OllamaError::InvalidInput("client certificate and key must be given together".to_string())),
+        }
+    }
+}
+
+/// 出站连接绑定的本地地址
+#[derive(Debug, Clone)]
+pub struct SourceAddress {
+    pub address: IpAddr,
+    /// 按网卡指定时记录网卡名
+    pub interface: Option<String>,
+}
+
+impl SourceAddress {
+    /// 使用网卡上的地址，优先 IPv4，跳过链路本地地址
+    pub fn from_interface(name: &str) -> Result<Self> {
+        let addresses: Vec<IpAddr> = if_addrs::get_if_addrs()?
+            .into_iter()
+            .filter(|iface| iface.name == name && !iface.is_link_local())
+            .map(|iface| iface.ip())
+            .collect();
+        let address = addresses.iter().find(|ip| ip.is_ipv4())
+            .or_else(|| addresses.first())
+            .copied()
+            .ok_or_else(|| OllamaError::InvalidInput(format!("No usable address on interface {}", name)))?;
+
+        Ok(Self {
+            address,
+            interface: Some(name.to_string()),
+        })
+    }
+}
+
+/// 未配置时使用的 User-Agent，让网络归属方能识别扫描来源
+pub const DEFAULT_USER_AGENT: &str = concat!("free-ollama/", env!("CARGO_PKG_VERSION"));
+
+/// 写入报告时隐藏值的请求头
+const SENSITIVE_HEADERS: &[&str] = &["authorization", "proxy-authorization", "cookie"];
+
+/// 扫描器配置
+#[derive(Debug, Clone, Default)]
+pub struct ScannerConfig {
+    pub timeouts: TimeoutConfig,
+    /// 按网段RTT动态调整建连和握手超时，None 表示使用固定超时
+    pub adaptive_timeouts: Option<AdaptiveTimeoutConfig>,
+    pub retry: RetryPolicy,
+    /// 同时进行的请求数上限，None 表示不限制；两阶段扫描时只作用于第二阶段
+    pub max_concurrency: Option<usize>,
+    /// 每秒发出的请求数上限，None 表示不限制
+    pub rate_limit: Option<u32>,
+    /// 首次探测失败时改用另一种协议 (HTTP/HTTPS) 再试一次
+    pub scheme_fallback: bool,
+    pub redirect: RedirectPolicy,
+    pub tls: TlsConfig,
+    pub proxy: ProxyConfig,
+    /// 出站连接的源地址，None 表示由系统选择
+    pub source: Option<SourceAddress>,
+    /// None 时使用 DEFAULT_USER_AGENT
+    pub user_agent: Option<String>,
+    /// 每个请求附带的额外请求头，例如联系方式
+    pub headers: Vec<(String, String)>,
+    /// 受保护网关的凭据，不会写入结果
+    pub credentials: CredentialStore,
+    /// Ollama 之外要识别的服务类型，只在目标不是 Ollama 时运行
+    pub detectors: DetectorRegistry,
+    /// 探测模板：目标不是 Ollama 时排在内置检测器之前尝试，是 Ollama 时补充识别依据
+    pub templates: DetectorRegistry,
+    /// 启用两阶段扫描，None 表示直接做完整探测
+    pub banner: Option<BannerCheck>,
+    /// 对 Ollama 服务额外请求 /api/ps，记录当前加载的模型
+    pub probe_loaded_models: bool,
+    /// 对 Ollama 服务的模型请求 /api/show 补全详细信息，值为每个目标的请求上限，None 表示不补全
+    pub model_show_limit: Option<usize>,
+}
+
+impl ScannerConfig {
+    /// 是否需要识别 Ollama 之外的服务
+    pub fn has_detectors(&self) -> bool {
+        !self.detectors.is_empty() || !self.templates.is_empty()
+    }
+
+    pub fn user_agent(&self) -> &str {
+        self.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT)
+    }
+
+    /// 写入扫描汇总的环境信息，敏感请求头只保留名称
+    pub fn metadata(&self) -> ScanMetadata {
+        ScanMetadata {
+            source_address: self.source.as_ref().map(|source| source.address.to_string()),
+            source_interface: self.source.as_ref().and_then(|source| source.interface.clone()),
+            user_agent: Some(self.user_agent().to_string()),
+            headers: self.headers.iter()
+                .map(|(name, value)| {
+                    let value = if SENSITIVE_HEADERS.contains(&name.to_lowercase().as_str()) { "<redacted>" } else { value.as_str() };
+                    (name.clone(), value.to_string())
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration_accepts_plain_decimals() {
+        for (value, millis) in [
+            ("500ms", 500),
+            ("1ms", 1),
+            ("2s", 2000),
+            ("1.5", 1500),
+            ("1.5s", 1500),
+            (" 0.25s ", 250),
+            ("3600s", 3_600_000),
+            ("3600000ms", 3_600_000),
+        ] {
+            assert_eq!(parse_duration(value), Ok(Duration::from_millis(millis)), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn parse_duration_rejects_other_forms_and_out_of_range_values() {
+        for value in [
+            "", "s", "ms", "1e9", "1e-3s", "inf", "infinity", "NaN", "-1", "-1s", "+1s",
+            ".5", "1.2.3", "0x10", "1 000ms", "0", "0s", "0.5ms", "0.0001", "3601s", "3600001ms", "1h",
+        ] {
+            assert!(parse_duration(value).is_err(), "{:?} should be rejected", value);
+        }
+    }
+}
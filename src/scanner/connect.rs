@@ -0,0 +1,296 @@
+use std::time::{Duration, Instant};
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use hyper::client::conn::{self, SendRequest};
+use hyper::header::{HeaderValue, HOST};
+use hyper::Body;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{lookup_host, TcpSocket, TcpStream};
+use tokio::time::timeout;
+use tokio_native_tls::TlsConnector;
+use crate::{Result, OllamaError};
+use reqwest::Url;
+use url::Position;
+use crate::scanner::{hyper_failure, io_failure, parse_certificate, AdaptiveTimeouts, ProxyRoutes, ScannerConfig, SourceAddress, TimeoutConfig};
+use crate::storage::{AppliedTimeouts, CertificateInfo, FailureKind, ScanFailure, Target, TimingBreakdown};
+
+/// 预连接的结果、实际使用的超时、各阶段耗时及服务端证书
+pub struct ConnectOutcome {
+    pub timeouts: AppliedTimeouts,
+    pub timing: TimingBreakdown,
+    pub certificate: Option<CertificateInfo>,
+    /// 出口路径：direct 或使用的代理
+    pub egress: String,
+    /// TCP 建连成功的对端地址，经代理访问时为 None
+    pub peer: Option<IpAddr>,
+    /// 建立好的连接，经代理访问时为 None
+    pub result: std::result::Result<Option<Connection>, ScanFailure>,
+}
+
+trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+/// 预连接建立的 HTTP/1 连接，请求直接在这条连接上发送
+pub struct Connection {
+    sender: SendRequest<Body>,
+}
+
+impl Connection {
+    /// 在这条连接上发送请求，返回响应头；请求中缺少 Host 头时按URL补上
+    pub async fn send(&mut self, request: reqwest::Request) -> std::result::Result<hyper::Response<Body>, ScanFailure> {
+        let url = request.url();
+        let path = &url[Position::BeforePath..Position::AfterQuery];
+        let host = &url[Position::BeforeHost..Position::AfterPort];
+
+        let mut builder = hyper::Request::builder().method(request.method().clone()).uri(path);
+        if let Some(headers) = builder.headers_mut() {
+            *headers = request.headers().clone();
+            if !headers.contains_key(HOST) {
+                if let Ok(value) = HeaderValue::from_str(host) {
+                    headers.insert(HOST, value);
+                }
+            }
+        }
+        let body = request.body().and_then(reqwest::Body::as_bytes).map(|bytes| Body::from(bytes.to_vec())).unwrap_or_else(Body::empty);
+        let request = builder.body(body).map_err(|e| ScanFailure {
+            kind: FailureKind::Other,
+            message: format!("invalid request: {}", e),
+        })?;
+
+        std::future::poll_fn(|cx| self.sender.poll_ready(cx)).await.map_err(|e| hyper_failure(&e))?;
+        self.sender.send_request(request).await.map_err(|e| hyper_failure(&e))
+    }
+}
+
+/// 在发送HTTP请求前单独完成 DNS、TCP 建连和 TLS 握手，分别套用各自的超时
+///
+/// reqwest 只能为建连和握手设置一个合并的超时，因此这里自行建立连接，
+/// 握手完成后直接在同一条连接上发送 HTTP/1 请求，各阶段耗时与超时都对应这条连接。
+/// 经代理访问的目标无法直连，跳过预连接，由 reqwest 经代理发送请求。
+pub struct ConnectionProber {
+    tls: TlsConnector,
+    /// 证书校验失败时只用于读取证书，不在这条连接上发送请求
+    inspect_tls: Option<TlsConnector>,
+    timeouts: TimeoutConfig,
+    adaptive: Option<AdaptiveTimeouts>,
+    proxy: Option<ProxyRoutes>,
+    source: Option<SourceAddress>,
+}
+
+impl ConnectionProber {
+    pub fn new(config: &ScannerConfig, proxy: Option<ProxyRoutes>) -> Result<Self> {
+        // 证书校验与 reqwest 客户端一致：额外的根证书、客户端证书和 --insecure
+        let identity = config.tls.client_identity()?
+            .map(|(cert, key)| native_tls::Identity::from_pkcs8(&cert, &key))
+            .transpose()?;
+        let mut roots = Vec::new();
+        for path in &config.tls.ca_certs {
+            let pem = std::fs::read(path)?;
+            for item in x509_parser::pem::Pem::iter_from_buffer(&pem) {
+                let item = item.map_err(|e| OllamaError::InvalidInput(format!("Invalid PEM in {}: {}", path, e)))?;
+                roots.push(native_tls::Certificate::from_der(&item.contents)?);
+            }
+        }
+        let connector = |accept_invalid: bool| -> Result<TlsConnector> {
+            let mut builder = native_tls::TlsConnector::builder();
+            builder
+                .danger_accept_invalid_certs(accept_invalid)
+                .danger_accept_invalid_hostnames(accept_invalid);
+            for root in &roots {
+                builder.add_root_certificate(root.clone());
+            }
+            if let Some(identity) = &identity {
+                builder.identity(identity.clone());
+            }
+            Ok(TlsConnector::from(builder.build()?))
+        };
+
+        let accept_invalid = config.tls.accept_invalid_certs;
+        Ok(Self {
+            tls: connector(accept_invalid)?,
+            inspect_tls: (!accept_invalid).then(|| connector(true)).transpose()?,
+            timeouts: config.timeouts.clone(),
+            adaptive: config.adaptive_timeouts.clone().map(AdaptiveTimeouts::new).transpose()?,
+            proxy,
+            source: config.source.clone(),
+        })
+    }
+
+    /// 建立到目标的连接，失败时返回失败原因
+    pub async fn probe(&self, target: &Target) -> ConnectOutcome {
+        let timeouts = AppliedTimeouts {
+            connect_ms: self.timeouts.connect.as_millis() as u64,
+            tls_handshake_ms: target.is_https.then_some(self.timeouts.tls_handshake.as_millis() as u64),
+            request_ms: self.timeouts.request.as_millis() as u64,
+            ..Default::default()
+        };
+        let mut outcome = ConnectOutcome {
+            timeouts,
+            timing: TimingBreakdown::default(),
+            certificate: None,
+            egress: "direct".to_string(),
+            peer: None,
+            result: Ok(None),
+        };
+
+        if let Some(egress) = self.proxy_egress(target) {
+            outcome.egress = egress;
+            return outcome;
+        }
+
+        outcome.result = self.connect(target, &mut outcome).await.map(Some);
+        outcome
+    }
+
+    /// 目标经代理访问时返回出口路径
+    pub fn proxy_egress(&self, target: &Target) -> Option<String> {
+        let routes = self.proxy.as_ref()?;
+        let url = Url::parse(&target.base_url()).ok()?;
+        routes.proxy_for(&url).is_some().then(|| routes.egress(&url))
+    }
+
+    async fn tcp_connect(&self, addr: SocketAddr) -> io::Result<TcpStream> {
+        let Some(source) = &self.source else {
+            return TcpStream::connect(addr).await;
+        };
+        let socket = if addr.is_ipv4() { TcpSocket::new_v4()? } else { TcpSocket::new_v6()? };
+        socket.bind(SocketAddr::new(source.address, 0))?;
+        socket.connect(addr).await
+    }
+
+    async fn connect(&self, target: &Target, outcome: &mut ConnectOutcome) -> std::result::Result<Connection, ScanFailure> {
+        let host = target.host.trim_start_matches('[').trim_end_matches(']');
+
+        let dns_start = Instant::now();
+        let mut addrs: Vec<_> = timeout(self.timeouts.connect, lookup_host((host, target.port)))
+            .await
+            .map_err(|_| ScanFailure {
+                kind: FailureKind::DnsFailure,
+                message: format!("DNS lookup timed out after {:?}", self.timeouts.connect),
+            })?
+            .map_err(|e| ScanFailure {
+                kind: FailureKind::DnsFailure,
+                message: format!("failed to resolve {}: {}", host, e),
+            })?
+            .collect();
+        outcome.timing.dns_ms = Some(dns_start.elapsed().as_millis() as u64);
+        if addrs.is_empty() {
+            return Err(ScanFailure {
+                kind: FailureKind::DnsFailure,
+                message: format!("no addresses found for {}", host),
+            });
+        }
+
+        // 绑定源地址时只能连接同一地址族
+        if let Some(source) = &self.source {
+            addrs.retain(|addr| addr.is_ipv4() == source.address.is_ipv4());
+        }
+        let first = addrs.first().ok_or_else(|| ScanFailure {
+            kind: FailureKind::Other,
+            message: format!("no addresses of {} in the same family as source address {}", host, self.source.as_ref().map(|s| s.address.to_string()).unwrap_or_default()),
+        })?;
+
+        // 同网段已有足够的RTT样本时使用自适应超时
+        let network = AdaptiveTimeouts::network_key(first.ip());
+        let mut connect_timeout = self.timeouts.connect;
+        let mut tls_timeout = self.timeouts.tls_handshake;
+        if let Some(adaptive) = &self.adaptive {
+            if let Some((connect, tls)) = adaptive.timeouts_for(&network) {
+                connect_timeout = connect;
+                tls_timeout = tls;
+                outcome.timeouts.connect_ms = connect.as_millis() as u64;
+                outcome.timeouts.tls_handshake_ms = target.is_https.then_some(tls.as_millis() as u64);
+                outcome.timeouts.adaptive = true;
+            }
+            outcome.timeouts.network = Some(network.clone());
+        }
+
+        let connect = async {
+            let mut last_failure = None;
+            for addr in &addrs {
+                let start = Instant::now();
+                match self.tcp_connect(*addr).await {
+                    Ok(stream) => return Ok((stream, *addr, start.elapsed())),
+                    Err(e) => {
+                        // 被拒绝也完成了一次往返，同样是有效的 RTT 样本
+                        if let (Some(adaptive), io::ErrorKind::ConnectionRefused) = (&self.adaptive, e.kind()) {
+                            adaptive.record(&AdaptiveTimeouts::network_key(addr.ip()), start.elapsed());
+                        }
+                        last_failure = Some(io_failure(&e));
+                    }
+                }
+            }
+            Err(last_failure.unwrap_or_else(|| io_failure(&std::io::ErrorKind::NotConnected.into())))
+        };
+
+        let (stream, addr, rtt) = match timeout(connect_timeout, connect).await {
+            Ok(result) => result?,
+            Err(_) => {
+                if let Some(adaptive) = &self.adaptive {
+                    adaptive.record_timeout(&network);
+                }
+                return Err(ScanFailure {
+                    kind: FailureKind::ConnectTimeout,
+                    message: format!("connect timed out after {:?}", connect_timeout),
+                });
+            }
+        };
+        outcome.peer = Some(addr.ip());
+
+        outcome.timing.connect_ms = Some(rtt.as_millis() as u64);
+        if let Some(adaptive) = &self.adaptive {
+            adaptive.record(&network, rtt);
+        }
+
+        let stream: Box<dyn Stream> = if target.is_https {
+            let tls_start = Instant::now();
+            let sni = target.vhost.as_deref().unwrap_or(host);
+            let stream = timeout(tls_timeout, self.tls.connect(sni, stream))
+                .await
+                .map_err(|_| ScanFailure {
+                    kind: FailureKind::TlsHandshake,
+                    message: format!("TLS handshake timed out after {:?}", tls_timeout),
+                })?;
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    // 证书未通过校验时仍然记录证书，另行握手一次但不发送请求
+                    if let Some(inspect) = &self.inspect_tls {
+                        outcome.certificate = self.inspect_certificate(inspect, addr, sni, connect_timeout, tls_timeout).await;
+                    }
+                    return Err(ScanFailure {
+                        kind: FailureKind::TlsHandshake,
+                        message: format!("TLS handshake failed: {}", e),
+                    });
+                }
+            };
+            outcome.timing.tls_ms = Some(tls_start.elapsed().as_millis() as u64);
+            outcome.certificate = peer_certificate(&stream);
+            Box::new(stream)
+        } else {
+            Box::new(stream)
+        };
+
+        let (sender, connection) = conn::handshake(stream).await.map_err(|e| hyper_failure(&e))?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                log::debug!("Connection to {} closed: {}", addr, e);
+            }
+        });
+        Ok(Connection { sender })
+    }
+
+    /// 不校验证书重新握手，只读取服务端证书
+    async fn inspect_certificate(&self, tls: &TlsConnector, addr: SocketAddr, sni: &str, connect_timeout: Duration, tls_timeout: Duration) -> Option<CertificateInfo> {
+        let stream = timeout(connect_timeout, self.tcp_connect(addr)).await.ok()?.ok()?;
+        let stream = timeout(tls_timeout, tls.connect(sni, stream)).await.ok()?.ok()?;
+        peer_certificate(&stream)
+    }
+}
+
+fn peer_certificate(stream: &tokio_native_tls::TlsStream<TcpStream>) -> Option<CertificateInfo> {
+    stream.get_ref().peer_certificate().ok().flatten()
+        .and_then(|cert| cert.to_der().ok())
+        .and_then(|der| parse_certificate(&der))
+}
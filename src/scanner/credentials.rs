@@ -0,0 +1,273 @@
+use ipnet::IpNet;
+use serde::Deserialize;
+use std::fmt;
+use std::net::IpAddr;
+use crate::{Result, OllamaError};
+use crate::storage::Target;
+
+/// 未匹配凭据文件时使用的 Bearer token
+pub const BEARER_TOKEN_ENV: &str = "FREE_OLLAMA_BEARER_TOKEN";
+/// 未匹配凭据文件时使用的 Basic 认证 (`user:pass`)
+pub const BASIC_AUTH_ENV: &str = "FREE_OLLAMA_BASIC_AUTH";
+/// 环境变量凭据的适用范围，逗号分隔，必须设置且不能为 `*`
+pub const CREDENTIAL_SCOPE_ENV: &str = "FREE_OLLAMA_CREDENTIAL_SCOPE";
+/// 设为 `1` 或 `true` 时环境变量凭据也发往 http 目标
+pub const CREDENTIAL_ALLOW_HTTP_ENV: &str = "FREE_OLLAMA_CREDENTIAL_ALLOW_HTTP";
+
+/// 访问受保护网关的凭据，Debug 输出不包含明文
+#[derive(Clone)]
+pub enum Credential {
+    Bearer(String),
+    Basic { username: String, password: String },
+}
+
+impl fmt::Debug for Credential {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Credential::Bearer(_) => write!(f, "Bearer(<redacted>)"),
+            Credential::Basic { username, .. } => write!(f, "Basic({}:<redacted>)", username),
+        }
+    }
+}
+
+/// 凭据的适用范围：IP、CIDR、主机名或 `*.example.com` 形式的域名后缀
+#[derive(Debug, Clone)]
+enum Scope {
+    Network(IpNet),
+    Host(String),
+    Suffix(String),
+}
+
+impl Scope {
+    /// 不接受 `*`、`0.0.0.0/0` 等匹配任意主机的范围，避免扫描时把凭据发给所有目标
+    fn parse(value: &str) -> Result<Self> {
+        let value = value.trim().to_lowercase();
+        let scope = if let Ok(net) = value.parse::<IpNet>() {
+            Scope::Network(net)
+        } else if let Ok(ip) = value.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+            Scope::Network(IpNet::from(ip))
+        } else {
+            match value.strip_prefix("*.").or_else(|| value.strip_prefix('.')) {
+                Some(suffix) => Scope::Suffix(suffix.to_string()),
+                None => Scope::Host(value.clone()),
+            }
+        };
+        let wildcard = match &scope {
+            Scope::Network(net) => net.prefix_len() == 0,
+            Scope::Host(name) | Scope::Suffix(name) => name.is_empty() || name.contains('*'),
+        };
+        if wildcard {
+            return Err(OllamaError::InvalidInput(format!(
+                "Credential scope '{}' is not an explicit host, domain or network (no '*')", value
+            )));
+        }
+        Ok(scope)
+    }
+
+    fn matches(&self, host: &str) -> bool {
+        let host = host.trim_start_matches('[').trim_end_matches(']').to_lowercase();
+        match self {
+            Scope::Network(net) => host.parse::<IpAddr>().is_ok_and(|ip| net.contains(&ip)),
+            Scope::Host(name) => host == *name,
+            Scope::Suffix(suffix) => host.ends_with(&format!(".{}", suffix)),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CredentialEntry {
+    scope: String,
+    bearer_token: Option<String>,
+    bearer_token_env: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+    password_env: Option<String>,
+    /// 默认只经 https 发送
+    #[serde(default)]
+    allow_http: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CredentialFile {
+    #[serde(default)]
+    credential: Vec<CredentialEntry>,
+}
+
+/// 匹配到目标的凭据
+#[derive(Debug, Clone)]
+pub struct ScopedCredential {
+    /// 配置中的范围原文，写入结果的 `credential_scope`
+    pub scope: String,
+    pub credential: Credential,
+    /// 是否允许经明文 http 发送
+    pub allow_http: bool,
+    matchers: Vec<Scope>,
+}
+
+impl ScopedCredential {
+    /// `scope` 为逗号分隔的 IP、CIDR、主机名或域名后缀，至少一项
+    pub fn new(scope: &str, credential: Credential, allow_http: bool) -> Result<Self> {
+        let matchers = scope.split(',')
+            .filter(|value| !value.trim().is_empty())
+            .map(Scope::parse)
+            .collect::<Result<Vec<_>>>()?;
+        if matchers.is_empty() {
+            return Err(OllamaError::InvalidInput("Credential scope must list at least one host, domain or network".to_string()));
+        }
+        Ok(Self { scope: scope.to_string(), credential, allow_http, matchers })
+    }
+}
+
+/// 按目标选择凭据，凭据文件按顺序匹配，均未匹配时使用环境变量中的凭据
+#[derive(Debug, Clone, Default)]
+pub struct CredentialStore {
+    entries: Vec<ScopedCredential>,
+}
+
+impl CredentialStore {
+    /// 只读取环境变量中的凭据，设置了凭据时必须用 FREE_OLLAMA_CREDENTIAL_SCOPE 限定范围
+    pub fn from_env() -> Result<Self> {
+        let credential = match (std::env::var(BEARER_TOKEN_ENV).ok(), std::env::var(BASIC_AUTH_ENV).ok()) {
+            (Some(token), _) if !token.is_empty() => Credential::Bearer(token),
+            (_, Some(basic)) if !basic.is_empty() => {
+                let (username, password) = basic.split_once(':').ok_or_else(|| {
+                    OllamaError::InvalidInput(format!("{} must be in the form user:pass", BASIC_AUTH_ENV))
+                })?;
+                Credential::Basic { username: username.to_string(), password: password.to_string() }
+            }
+            _ => return Ok(Self::default()),
+        };
+
+        let scope = std::env::var(CREDENTIAL_SCOPE_ENV).unwrap_or_default();
+        let allow_http = std::env::var(CREDENTIAL_ALLOW_HTTP_ENV)
+            .is_ok_and(|value| matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes"));
+        let entry = ScopedCredential::new(&scope, credential, allow_http).map_err(|e| OllamaError::InvalidInput(format!(
+            "{} or {} requires {} with explicit hosts, domains or networks: {}",
+            BEARER_TOKEN_ENV, BASIC_AUTH_ENV, CREDENTIAL_SCOPE_ENV, e
+        )))?;
+        Ok(Self { entries: vec![entry] })
+    }
+
+    /// 读取 TOML 凭据文件，环境变量中的凭据排在文件条目之后
+    pub fn from_file(path: &str) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let file: CredentialFile = toml::from_str(&content).map_err(|e| {
+            OllamaError::ParseError(format!("Invalid credentials file '{}': {}", path, e))
+        })?;
+
+        let mut entries = Vec::new();
+        for entry in file.credential {
+            let scoped = ScopedCredential::new(&entry.scope, Self::credential(&entry)?, entry.allow_http)
+                .map_err(|e| OllamaError::InvalidInput(format!("Credentials file '{}': {}", path, e)))?;
+            entries.push(scoped);
+        }
+        entries.extend(Self::from_env()?.entries);
+        Ok(Self { entries })
+    }
+
+    fn credential(entry: &CredentialEntry) -> Result<Credential> {
+        let env = |name: &str| std::env::var(name).map_err(|_| {
+            OllamaError::InvalidInput(format!("Environment variable {} for scope '{}' is not set", name, entry.scope))
+        });
+
+        let token = match (&entry.bearer_token, &entry.bearer_token_env) {
+            (Some(token), _) => Some(token.clone()),
+            (None, Some(name)) => Some(env(name)?),
+            (None, None) => None,
+        };
+        match (token, &entry.username) {
+            (Some(token), None) => Ok(Credential::Bearer(token)),
+            (None, Some(username)) => {
+                let password = match (&entry.password, &entry.password_env) {
+                    (Some(password), _) => password.clone(),
+                    (None, Some(name)) => env(name)?,
+                    (None, None) => String::new(),
+                };
+                Ok(Credential::Basic { username: username.clone(), password })
+            }
+            _ => Err(OllamaError::InvalidInput(format!(
+                "Credential for scope '{}' needs either a bearer token or a username", entry.scope
+            ))),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 目标主机或虚拟主机名匹配的凭据
+    pub fn credential_for(&self, target: &Target) -> Option<&ScopedCredential> {
+        let hosts = [Some(target.host.as_str()), target.vhost.as_deref()];
+        self.entries.iter().find(|entry| {
+            entry.matchers.iter().any(|scope| hosts.iter().flatten().any(|host| scope.matches(host)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bearer(scope: &str) -> ScopedCredential {
+        ScopedCredential::new(scope, Credential::Bearer("token".to_string()), false).unwrap()
+    }
+
+    fn target(host: &str, vhost: Option<&str>) -> Target {
+        Target {
+            host: host.to_string(),
+            port: 443,
+            is_https: true,
+            vhost: vhost.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn wildcard_scopes_are_rejected() {
+        for scope in ["*", "", " , ", "0.0.0.0/0", "::/0", "*.", "*.*", "10.0.0.0/8,*"] {
+            assert!(ScopedCredential::new(scope, Credential::Bearer("token".to_string()), false).is_err(), "{:?}", scope);
+        }
+    }
+
+    #[test]
+    fn network_scopes_match_addresses_only() {
+        let scope = Scope::parse("10.0.0.0/8").unwrap();
+        assert!(scope.matches("10.1.2.3"));
+        assert!(!scope.matches("11.0.0.1"));
+        assert!(!scope.matches("10.example.com"));
+
+        let v6 = Scope::parse("2001:db8::/32").unwrap();
+        assert!(v6.matches("[2001:db8::1]"));
+        assert!(!v6.matches("[2001:db9::1]"));
+        assert!(Scope::parse("192.168.1.5").unwrap().matches("192.168.1.5"));
+        assert!(!Scope::parse("192.168.1.5").unwrap().matches("192.168.1.6"));
+    }
+
+    #[test]
+    fn suffix_scopes_match_subdomains_only() {
+        let scope = Scope::parse("*.example.com").unwrap();
+        assert!(scope.matches("gw.example.com"));
+        assert!(scope.matches("A.B.Example.com"));
+        assert!(!scope.matches("example.com"));
+        assert!(!scope.matches("badexample.com"));
+        assert!(!scope.matches("example.com.evil.net"));
+
+        let host = Scope::parse("gw.example.com").unwrap();
+        assert!(host.matches("GW.example.com"));
+        assert!(!host.matches("x.gw.example.com"));
+    }
+
+    #[test]
+    fn credential_for_matches_host_or_vhost_in_order() {
+        let store = CredentialStore {
+            entries: vec![bearer("*.example.com"), bearer("10.0.0.0/8"), bearer("10.1.0.0/16")],
+        };
+        let scope = |target: &Target| store.credential_for(target).map(|scoped| scoped.scope.clone());
+        assert_eq!(scope(&target("203.0.113.7", Some("gw.example.com"))).as_deref(), Some("*.example.com"));
+        assert_eq!(scope(&target("10.1.2.3", None)).as_deref(), Some("10.0.0.0/8"));
+        assert_eq!(scope(&target("203.0.113.7", None)), None);
+        assert_eq!(scope(&target("203.0.113.7", Some("example.org"))), None);
+    }
+}
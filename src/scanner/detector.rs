@@ -0,0 +1,147 @@
+use reqwest::header::HeaderMap;
+use reqwest::Method;
+use std::fmt;
+use std::sync::Arc;
+use crate::{Result, OllamaError};
+use crate::scanner::products::{LlamaCppDetector, LmStudioDetector, LocalAiDetector, OpenAiCompatibleDetector, TgiDetector, VllmDetector};
+use crate::storage::{AuthenticityIndicator, ModelInfo};
+
+/// 内置的 Ollama 检测在结果中使用的产品名
+pub const OLLAMA_PRODUCT: &str = "ollama";
+
+/// 检测器需要发出的一个请求，同一目标上相同的请求只发送一次
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProbeRequest {
+    pub method: Method,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+}
+
+impl ProbeRequest {
+    pub fn get(path: &str) -> Self {
+        Self {
+            method: Method::GET,
+            path: path.to_string(),
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+}
+
+/// 请求得到的响应
+#[derive(Debug, Clone)]
+pub struct ProbeResponse {
+    pub status: u16,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+impl ProbeResponse {
+    /// 200 响应的 JSON 内容
+    pub fn json(&self) -> Option<serde_json::Value> {
+        if self.status != 200 {
+            return None;
+        }
+        serde_json::from_str(&self.body).ok()
+    }
+}
+
+/// 检测器识别出的服务信息
+#[derive(Debug, Clone, Default)]
+pub struct Detection {
+    pub version: Option<String>,
+    pub models: Vec<ModelInfo>,
+    pub confidence: f64,
+    pub indicators: Vec<AuthenticityIndicator>,
+}
+
+/// 一种自托管大模型服务的识别规则
+///
+/// 检测器只描述要发出的请求和如何解析响应，请求由扫描器统一发送，
+/// 因此多个检测器共用的路径（如 `/v1/models`）在每个目标上只请求一次。
+pub trait Detector: Send + Sync {
+    /// 产品名，写入结果的 `product` 字段
+    fn name(&self) -> &str;
+
+    fn probes(&self) -> Vec<ProbeRequest>;
+
+    /// `responses` 与 `probes()` 一一对应，请求失败时为 None
+    fn detect(&self, responses: &[Option<&ProbeResponse>]) -> Option<Detection>;
+}
+
+/// 已注册的检测器，按注册顺序尝试，第一个识别成功的生效
+#[derive(Clone)]
+pub struct DetectorRegistry {
+    detectors: Vec<Arc<dyn Detector>>,
+}
+
+impl fmt::Debug for DetectorRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+/// 默认不启用任何检测器，只检测 Ollama
+impl Default for DetectorRegistry {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl DetectorRegistry {
+    pub fn empty() -> Self {
+        Self { detectors: Vec::new() }
+    }
+
+    /// 全部内置检测器，通用的 OpenAI 兼容检测放在最后
+    pub fn builtin() -> Self {
+        let mut registry = Self::empty();
+        registry.register(Arc::new(LlamaCppDetector));
+        registry.register(Arc::new(VllmDetector));
+        registry.register(Arc::new(TgiDetector));
+        registry.register(Arc::new(LmStudioDetector));
+        registry.register(Arc::new(LocalAiDetector));
+        registry.register(Arc::new(OpenAiCompatibleDetector));
+        registry
+    }
+
+    /// 按名称选择内置检测器，`all` 表示全部，`none` 表示只检测 Ollama
+    pub fn select(names: &[String]) -> Result<Self> {
+        let builtin = Self::builtin();
+        if names.iter().any(|name| name == "all") {
+            return Ok(builtin);
+        }
+        let mut registry = Self::empty();
+        for name in names.iter().filter(|name| *name != "none") {
+            let detector = builtin.detectors.iter()
+                .find(|detector| detector.name().eq_ignore_ascii_case(name))
+                .ok_or_else(|| OllamaError::InvalidInput(format!(
+                    "Unknown detector '{}', available: {}", name, builtin.names().join(", ")
+                )))?;
+            registry.register(detector.clone());
+        }
+        Ok(registry)
+    }
+
+    pub fn register(&mut self, detector: Arc<dyn Detector>) {
+        self.detectors.push(detector);
+    }
+
+    /// 追加另一组检测器，排在已有检测器之后
+    pub fn extend(&mut self, other: DetectorRegistry) {
+        self.detectors.extend(other.detectors);
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.detectors.iter().map(|detector| detector.name()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.detectors.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<dyn Detector>> {
+        self.detectors.iter()
+    }
+}
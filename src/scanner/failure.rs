@@ -0,0 +1,130 @@
+use std::error::Error as StdError;
+use std::io;
+use crate::storage::{ErrorDetail, FailureKind, OllamaService, ScanFailure};
+
+/// 每种失败类型在汇总中保留的示例数量
+const MAX_EXAMPLES: usize = 3;
+
+/// 根据 reqwest 错误及其来源链判断失败类型
+pub fn classify_error(error: &reqwest::Error) -> ScanFailure {
+    let chain = error_chain(error);
+    let text = chain.to_lowercase();
+
+    let kind = if error.is_timeout() {
+        if error.is_connect() { FailureKind::ConnectTimeout } else { FailureKind::ReadTimeout }
+    } else if error.is_connect() {
+        match io_error_kind(error) {
+            Some(io::ErrorKind::ConnectionRefused) => FailureKind::ConnectionRefused,
+            Some(io::ErrorKind::ConnectionReset) | Some(io::ErrorKind::ConnectionAborted) => FailureKind::ConnectionReset,
+            Some(io::ErrorKind::TimedOut) => FailureKind::ConnectTimeout,
+            _ if text.contains("dns error") || text.contains("failed to lookup address") => FailureKind::DnsFailure,
+            _ if text.contains("ssl") || text.contains("tls") || text.contains("certificate") || text.contains("handshake") => FailureKind::TlsHandshake,
+            _ => FailureKind::Other,
+        }
+    } else if error.is_decode() || error.is_body() {
+        FailureKind::BodyDecode
+    } else if error.is_status() {
+        FailureKind::HttpStatus
+    } else {
+        match io_error_kind(error) {
+            Some(io::ErrorKind::ConnectionReset) | Some(io::ErrorKind::ConnectionAborted) | Some(io::ErrorKind::BrokenPipe) => FailureKind::ConnectionReset,
+            Some(io::ErrorKind::TimedOut) => FailureKind::ReadTimeout,
+            _ if text.contains("connection closed") || text.contains("incomplete message") => FailureKind::ConnectionReset,
+            _ => FailureKind::Other,
+        }
+    };
+
+    ScanFailure { kind, message: chain }
+}
+
+/// 建连阶段的IO错误
+pub fn io_failure(error: &io::Error) -> ScanFailure {
+    let kind = match error.kind() {
+        io::ErrorKind::ConnectionRefused => FailureKind::ConnectionRefused,
+        io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionAborted | io::ErrorKind::BrokenPipe => FailureKind::ConnectionReset,
+        io::ErrorKind::TimedOut => FailureKind::ConnectTimeout,
+        _ => FailureKind::Other,
+    };
+    ScanFailure { kind, message: error.to_string() }
+}
+
+/// 经预连接发送请求时的 hyper 错误，建连已经完成，只可能发生在读写阶段
+pub fn hyper_failure(error: &hyper::Error) -> ScanFailure {
+    let message = error_chain(error);
+    let kind = match io_error_kind(error) {
+        Some(io::ErrorKind::ConnectionReset) | Some(io::ErrorKind::ConnectionAborted) | Some(io::ErrorKind::BrokenPipe) => FailureKind::ConnectionReset,
+        Some(io::ErrorKind::TimedOut) => FailureKind::ReadTimeout,
+        _ if error.is_incomplete_message() || error.is_closed() => FailureKind::ConnectionReset,
+        _ => FailureKind::Other,
+    };
+    ScanFailure { kind, message }
+}
+
+/// 非 200 响应
+pub fn http_status_failure(status: u16) -> ScanFailure {
+    ScanFailure {
+        kind: FailureKind::HttpStatus,
+        message: format!("HTTP status {}", status),
+    }
+}
+
+/// 将错误及其全部来源拼接为一行，跳过已经包含在上层信息中的来源
+fn error_chain(error: &(dyn StdError + 'static)) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(err) = source {
+        let text = err.to_string();
+        if !message.contains(&text) {
+            message.push_str(": ");
+            message.push_str(&text);
+        }
+        source = err.source();
+    }
+    message
+}
+
+fn io_error_kind(error: &(dyn StdError + 'static)) -> Option<io::ErrorKind> {
+    let mut source = error.source();
+    while let Some(err) = source {
+        if let Some(io_err) = err.downcast_ref::<io::Error>() {
+            return Some(io_err.kind());
+        }
+        source = err.source();
+    }
+    None
+}
+
+/// 按失败类型汇总，百分比相对于全部目标
+pub fn summarize_failures(services: &[OllamaService]) -> Vec<ErrorDetail> {
+    let total = services.len().max(1) as f64;
+    let mut details: Vec<ErrorDetail> = Vec::new();
+
+    for service in services {
+        let Some(failure) = &service.failure else { continue };
+        let error_type = failure.kind.as_str();
+        let index = match details.iter().position(|d| d.error_type == error_type) {
+            Some(index) => index,
+            None => {
+                details.push(ErrorDetail {
+                    error_type: error_type.to_string(),
+                    count: 0,
+                    percentage: 0.0,
+                    examples: vec![],
+                });
+                details.len() - 1
+            }
+        };
+
+        let detail = &mut details[index];
+        detail.count += 1;
+        if detail.examples.len() < MAX_EXAMPLES {
+            detail.examples.push(format!("{}: {}", service.target.base_url(), failure.message));
+        }
+    }
+
+    for detail in &mut details {
+        detail.percentage = detail.count as f64 * 100.0 / total;
+    }
+    details.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.error_type.cmp(&b.error_type)));
+    details
+}
@@ -1,3 +1,28 @@
+pub mod adaptive;
+pub mod auth;
+pub mod certificate;
+pub mod client;
+pub mod config;
+pub mod connect;
+pub mod credentials;
+pub mod detector;
+pub mod failure;
+pub mod products;
+pub mod proxy;
+pub mod rate_limit;
 pub mod simple_scan;
+pub mod template;
 
-pub use simple_scan::*;
\ No newline at end of file
+pub use adaptive::*;
+pub use auth::*;
+pub use certificate::*;
+pub use client::*;
+pub use config::*;
+pub use connect::*;
+pub use credentials::*;
+pub use detector::*;
+pub use failure::*;
+pub use proxy::*;
+pub use rate_limit::*;
+pub use simple_scan::*;
+pub use template::*;
\ No newline at end of file
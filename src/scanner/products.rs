@@ -0,0 +1,226 @@
+use serde_json::Value;
+use crate::scanner::{Detection, Detector, ProbeRequest, ProbeResponse};
+use crate::storage::{AuthenticityIndicator, ModelInfo};
+
+fn indicator(indicator_type: &str, value: &str, confidence: f64, description: &str) -> AuthenticityIndicator {
+    AuthenticityIndicator {
+        indicator_type: indicator_type.to_string(),
+        value: value.to_string(),
+        confidence,
+        description: description.to_string(),
+    }
+}
+
+fn model(name: &str) -> ModelInfo {
+    ModelInfo {
+        name: name.to_string(),
+        size: None,
+        modified_at: None,
+        digest: None,
+        details: None,
+        metadata: None,
+        canonical: None,
+    }
+}
+
+/// OpenAI 风格 `/v1/models` 响应中的模型列表
+fn openai_models(response: Option<&ProbeResponse>) -> Option<Vec<Value>> {
+    let json = response?.json()?;
+    json.get("data")?.as_array().cloned()
+}
+
+fn model_ids(models: &[Value]) -> Vec<ModelInfo> {
+    models.iter()
+        .filter_map(|m| m.get("id").and_then(Value::as_str))
+        .map(model)
+        .collect()
+}
+
+fn owned_by(models: &[Value], owner: &str) -> bool {
+    models.iter().any(|m| m.get("owned_by").and_then(Value::as_str) == Some(owner))
+}
+
+fn string_field(json: &Value, field: &str) -> Option<String> {
+    json.get(field).and_then(Value::as_str).map(str::to_string)
+}
+
+/// llama.cpp server：`/props` 返回默认生成参数和槽位数
+pub struct LlamaCppDetector;
+
+impl Detector for LlamaCppDetector {
+    fn name(&self) -> &str {
+        "llama.cpp"
+    }
+
+    fn probes(&self) -> Vec<ProbeRequest> {
+        vec![ProbeRequest::get("/props"), ProbeRequest::get("/v1/models")]
+    }
+
+    fn detect(&self, responses: &[Option<&ProbeResponse>]) -> Option<Detection> {
+        let props = responses[0]?.json()?;
+        if props.get("default_generation_settings").is_none() && props.get("total_slots").is_none() {
+            return None;
+        }
+
+        let mut models = openai_models(responses[1]).map(|m| model_ids(&m)).unwrap_or_default();
+        if models.is_empty() {
+            // 旧版本没有 /v1/models，从模型路径取文件名
+            if let Some(path) = props.get("model_path").and_then(Value::as_str) {
+                models.push(model(path.rsplit(['/', '\\']).next().unwrap_or(path)));
+            }
+        }
+        Some(Detection {
+            version: string_field(&props, "build_info"),
+            models,
+            confidence: 0.9,
+            indicators: vec![indicator("endpoint", "/props", 0.9, "llama.cpp server properties endpoint")],
+        })
+    }
+}
+
+/// vLLM：`/v1/models` 中 owned_by 为 vllm
+pub struct VllmDetector;
+
+impl Detector for VllmDetector {
+    fn name(&self) -> &str {
+        "vllm"
+    }
+
+    fn probes(&self) -> Vec<ProbeRequest> {
+        vec![ProbeRequest::get("/v1/models"), ProbeRequest::get("/version")]
+    }
+
+    fn detect(&self, responses: &[Option<&ProbeResponse>]) -> Option<Detection> {
+        let models = openai_models(responses[0])?;
+        if !owned_by(&models, "vllm") {
+            return None;
+        }
+        Some(Detection {
+            version: responses[1].and_then(ProbeResponse::json).and_then(|v| string_field(&v, "version")),
+            models: model_ids(&models),
+            confidence: 0.95,
+            indicators: vec![indicator("model_owner", "vllm", 0.95, "/v1/models reports owned_by=vllm")],
+        })
+    }
+}
+
+/// text-generation-inference：`/info` 返回 model_id 和路由信息
+pub struct TgiDetector;
+
+impl Detector for TgiDetector {
+    fn name(&self) -> &str {
+        "tgi"
+    }
+
+    fn probes(&self) -> Vec<ProbeRequest> {
+        vec![ProbeRequest::get("/info")]
+    }
+
+    fn detect(&self, responses: &[Option<&ProbeResponse>]) -> Option<Detection> {
+        let info = responses[0]?.json()?;
+        let router = info.get("router").and_then(Value::as_str) == Some("text-generation-router");
+        if !router && (info.get("model_id").is_none() || info.get("max_total_tokens").is_none()) {
+            return None;
+        }
+        Some(Detection {
+            version: string_field(&info, "version"),
+            models: string_field(&info, "model_id").map(|id| vec![model(&id)]).unwrap_or_default(),
+            confidence: if router { 0.95 } else { 0.8 },
+            indicators: vec![indicator("endpoint", "/info", 0.9, "text-generation-inference info endpoint")],
+        })
+    }
+}
+
+/// LM Studio：`/api/v0/models` 带 publisher、state 等字段
+pub struct LmStudioDetector;
+
+impl Detector for LmStudioDetector {
+    fn name(&self) -> &str {
+        "lm-studio"
+    }
+
+    fn probes(&self) -> Vec<ProbeRequest> {
+        vec![ProbeRequest::get("/api/v0/models"), ProbeRequest::get("/v1/models")]
+    }
+
+    fn detect(&self, responses: &[Option<&ProbeResponse>]) -> Option<Detection> {
+        if let Some(models) = openai_models(responses[0]) {
+            if models.iter().any(|m| m.get("publisher").is_some() && m.get("state").is_some()) {
+                return Some(Detection {
+                    models: model_ids(&models),
+                    confidence: 0.95,
+                    indicators: vec![indicator("endpoint", "/api/v0/models", 0.95, "LM Studio REST API model list")],
+                    ..Default::default()
+                });
+            }
+        }
+
+        let models = openai_models(responses[1])?;
+        owned_by(&models, "organization_owner").then(|| Detection {
+            models: model_ids(&models),
+            confidence: 0.7,
+            indicators: vec![indicator("model_owner", "organization_owner", 0.7, "/v1/models reports LM Studio's default owner")],
+            ..Default::default()
+        })
+    }
+}
+
+/// LocalAI：首页或 `/system` 中的后端列表
+pub struct LocalAiDetector;
+
+impl Detector for LocalAiDetector {
+    fn name(&self) -> &str {
+        "localai"
+    }
+
+    fn probes(&self) -> Vec<ProbeRequest> {
+        vec![ProbeRequest::get("/system"), ProbeRequest::get("/"), ProbeRequest::get("/v1/models"), ProbeRequest::get("/version")]
+    }
+
+    fn detect(&self, responses: &[Option<&ProbeResponse>]) -> Option<Detection> {
+        let mut indicators = Vec::new();
+        if responses[0].and_then(ProbeResponse::json).is_some_and(|system| system.get("backends").is_some()) {
+            indicators.push(indicator("endpoint", "/system", 0.8, "LocalAI system endpoint lists backends"));
+        }
+        if responses[1].is_some_and(|root| root.status == 200 && root.body.contains("LocalAI")) {
+            indicators.push(indicator("banner", "LocalAI", 0.8, "Web UI mentions LocalAI"));
+        }
+        if indicators.is_empty() {
+            return None;
+        }
+
+        Some(Detection {
+            version: responses[3].and_then(ProbeResponse::json).and_then(|v| string_field(&v, "version")),
+            models: openai_models(responses[2]).map(|m| model_ids(&m)).unwrap_or_default(),
+            confidence: if indicators.len() > 1 { 0.95 } else { 0.8 },
+            indicators,
+        })
+    }
+}
+
+/// 其他 OpenAI 兼容网关，只能确认 `/v1/models` 的格式
+pub struct OpenAiCompatibleDetector;
+
+impl Detector for OpenAiCompatibleDetector {
+    fn name(&self) -> &str {
+        "openai-compatible"
+    }
+
+    fn probes(&self) -> Vec<ProbeRequest> {
+        vec![ProbeRequest::get("/v1/models")]
+    }
+
+    fn detect(&self, responses: &[Option<&ProbeResponse>]) -> Option<Detection> {
+        let json = responses[0]?.json()?;
+        if json.get("object").and_then(Value::as_str) != Some("list") {
+            return None;
+        }
+        let models = json.get("data")?.as_array()?;
+        Some(Detection {
+            models: model_ids(models),
+            confidence: 0.6,
+            indicators: vec![indicator("endpoint", "/v1/models", 0.6, "OpenAI-compatible model list")],
+            ..Default::default()
+        })
+    }
+}
@@ -0,0 +1,45 @@
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::time::Instant;
+
+/// 全局请求限速，所有请求（包括重试）都需要先取得许可
+pub struct RateLimiter {
+    semaphore: Option<Semaphore>,
+    interval: Option<Duration>,
+    next_slot: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(max_concurrency: Option<usize>, requests_per_second: Option<u32>) -> Self {
+        Self {
+            semaphore: max_concurrency.filter(|&n| n > 0).map(Semaphore::new),
+            interval: requests_per_second
+                .filter(|&n| n > 0)
+                .map(|n| Duration::from_secs_f64(1.0 / n as f64)),
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// 先取得并发许可，再预约并等待发送时间片，许可在返回值被丢弃时释放
+    ///
+    /// 顺序不能颠倒：先预约时间片的话，等待许可期间时间片已经过去，
+    /// 拿到许可的请求会集中发出而超过限速。
+    pub async fn acquire(&self) -> Option<SemaphorePermit<'_>> {
+        let permit = match &self.semaphore {
+            Some(semaphore) => semaphore.acquire().await.ok(),
+            None => None,
+        };
+
+        if let Some(interval) = self.interval {
+            let slot = {
+                let mut next_slot = self.next_slot.lock().unwrap_or_else(|e| e.into_inner());
+                let slot = (*next_slot).max(Instant::now());
+                *next_slot = slot + interval;
+                slot
+            };
+            tokio::time::sleep_until(slot).await;
+        }
+        permit
+    }
+}
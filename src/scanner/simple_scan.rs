@@ -1,63 +1,293 @@
-use reqwest::Client;
-use std::time::Duration;
-use crate::storage::{OllamaService, Target, DetectionDetails, ModelInfo, ModelDetails};
+use hyper::body::HttpBody;
+use reqwest::header::{HeaderMap, LOCATION};
+use reqwest::Url;
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+use std::sync::Arc;
+use crate::storage::{AppliedTimeouts, AuthProtection, CertificateInfo, OllamaService, Target, DetectionDetails, EndpointResult, FailureKind, LoadedModel, ModelInfo, ModelDetails, ModelMetadata, ScanFailure, ScanSummary, TimingBreakdown};
 use crate::error::Result;
+use crate::utils::{model_census, normalize_models};
+use crate::scanner::{classify_error, detect_protection, hyper_failure, is_denied, proxy_signature, http_status_failure, summarize_failures, ClientFactory, Connection, ConnectionProber, Detection, Detector, ProbeRequest, ProbeResponse, BannerCheck, RateLimiter, RequestPlan, ScannerConfig, OLLAMA_PRODUCT};
 use serde::Deserialize;
 use indicatif::{ProgressBar, ProgressStyle};
 use std::time::Instant;
 
 use std::fs::File;
 use std::io::Write;
-use chrono::Local;
+use chrono::{DateTime, Local, Utc};
+
+#[derive(Debug, Deserialize)]
+struct ApiModelInfo {
+    name: String,
+    modified_at: String,
+    size: u64,
+    digest: String,
+    details: ApiModelDetails,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiModelDetails {
+    format: String,
+    family: String,
+    families: Vec<String>,
+    parameter_size: String,
+    quantization_level: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagsResponse {
+    models: Vec<ApiModelInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiLoadedModel {
+    name: String,
+    digest: Option<String>,
+    size: Option<u64>,
+    size_vram: Option<u64>,
+    expires_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ShowResponse {
+    license: Option<String>,
+    parameters: Option<String>,
+    #[serde(default)]
+    model_info: serde_json::Map<String, serde_json::Value>,
+    capabilities: Option<Vec<String>>,
+}
+
+impl ShowResponse {
+    fn into_metadata(self) -> ModelMetadata {
+        // 上下文长度的键带架构前缀，如 llama.context_length
+        let context_length = self.model_info.iter()
+            .find(|(key, _)| key.ends_with(".context_length"))
+            .and_then(|(_, value)| value.as_u64());
+        let license = self.license.as_deref()
+            .and_then(|text| text.lines().map(str::trim).find(|line| !line.is_empty()))
+            .map(|line| line.chars().take(120).collect());
+
+        let mut parameters: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for line in self.parameters.as_deref().unwrap_or_default().lines() {
+            if let Some((name, value)) = line.trim().split_once(char::is_whitespace) {
+                parameters.entry(name.to_string()).or_default()
+                    .push(value.trim().trim_matches('"').to_string());
+            }
+        }
+
+        ModelMetadata {
+            context_length,
+            capabilities: self.capabilities,
+            license,
+            parameters,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct VersionResponse {
+    version: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PsResponse {
+    models: Vec<ApiLoadedModel>,
+}
+
+/// 单次 /api/tags 请求的结果
+struct TagsProbe {
+    status_code: Option<u16>,
+    response_time: u64,
+    timeouts: AppliedTimeouts,
+    timing: TimingBreakdown,
+    certificate: Option<CertificateInfo>,
+    egress: String,
+    /// 首次连接的对端地址，用于补全主机名目标的网络归属
+    peer: Option<IpAddr>,
+    credential_scope: Option<String>,
+    /// 发生跳转时依次请求的URL
+    redirect_chain: Vec<String>,
+    /// 最后一次请求的URL
+    final_url: String,
+    /// 请求被认证网关拦截时的认证信息
+    protection: Option<AuthProtection>,
+    result: std::result::Result<TagsResponse, ScanFailure>,
+}
+
+/// Ollama 根路径返回的横幅
+const OLLAMA_BANNER: &str = "Ollama is running";
+
+/// 横幅检查最多读取的响应体字节数
+const BANNER_READ_LIMIT: usize = 1024;
+
+/// /api/tags 响应体的上限
+const TAGS_READ_LIMIT: usize = 8 * 1024 * 1024;
+
+/// 检测器、/api/ps 和 /api/show 响应体的上限
+const PROBE_READ_LIMIT: usize = 2 * 1024 * 1024;
+
+/// 一个请求的响应：直连时在预连接上发送，经代理访问及其余请求由 reqwest 发送
+enum HttpResponse {
+    Connection(hyper::Response<hyper::Body>),
+    Client(reqwest::Response),
+}
+
+impl HttpResponse {
+    fn status(&self) -> u16 {
+        match self {
+            HttpResponse::Connection(response) => response.status().as_u16(),
+            HttpResponse::Client(response) => response.status().as_u16(),
+        }
+    }
+
+    fn headers(&self) -> &HeaderMap {
+        match self {
+            HttpResponse::Connection(response) => response.headers(),
+            HttpResponse::Client(response) => response.headers(),
+        }
+    }
+
+    /// 读取响应体，超过 `limit` 字节或到达期限时失败
+    async fn body(self, limit: usize, deadline: tokio::time::Instant) -> std::result::Result<Vec<u8>, ScanFailure> {
+        let read = async {
+            let mut body = Vec::new();
+            match self {
+                HttpResponse::Connection(response) => {
+                    let mut stream = response.into_body();
+                    while let Some(chunk) = stream.data().await {
+                        body.extend_from_slice(&chunk.map_err(|e| hyper_failure(&e))?);
+                        if body.len() > limit {
+                            return Err(body_too_large(limit));
+                        }
+                    }
+                }
+                HttpResponse::Client(mut response) => {
+                    while let Some(chunk) = response.chunk().await.map_err(|e| classify_error(&e))? {
+                        body.extend_from_slice(&chunk);
+                        if body.len() > limit {
+                            return Err(body_too_large(limit));
+                        }
+                    }
+                }
+            }
+            Ok(body)
+        };
+        tokio::time::timeout_at(deadline, read).await.map_err(|_| ScanFailure {
+            kind: FailureKind::ReadTimeout,
+            message: "timed out reading response body".to_string(),
+        })?
+    }
+}
+
+fn body_too_large(limit: usize) -> ScanFailure {
+    ScanFailure {
+        kind: FailureKind::BodyDecode,
+        message: format!("response body exceeds {} bytes", limit),
+    }
+}
+
+fn progress_bar(total: usize) -> ProgressBar {
+    let pb = ProgressBar::new(total as u64);
+    pb.set_style(ProgressStyle::default_bar()
+        .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta}) {msg}")
+        .unwrap_or_else(|_| ProgressStyle::default_bar())
+        .progress_chars("#>-"));
+    pb
+}
+
+/// 与协议无关的失败（域名不存在、端口未开放）换协议重试没有意义
+fn scheme_may_help(kind: FailureKind) -> bool {
+    !matches!(kind, FailureKind::DnsFailure | FailureKind::ConnectionRefused | FailureKind::ConnectTimeout)
+}
 
 pub struct SimpleScanner;
 
 impl SimpleScanner {
-    fn save_results(services: &[OllamaService]) -> Result<String> {
+    /// 汇总扫描结果及失败原因
+    pub fn summarize(services: &[OllamaService], config: &ScannerConfig, start_time: DateTime<Utc>, end_time: DateTime<Utc>) -> ScanSummary {
+        ScanSummary {
+            start_time,
+            end_time,
+            total_targets: services.len(),
+            active_services: services.iter().filter(|s| s.is_active).count(),
+            protected_services: services.iter().filter(|s| s.is_protected()).count(),
+            other_services: services.iter()
+                .filter(|s| s.is_other_service())
+                .fold(BTreeMap::new(), |mut counts, s| {
+                    *counts.entry(s.product.clone().unwrap_or_default()).or_insert(0) += 1;
+                    counts
+                }),
+            error_details: summarize_failures(services),
+            model_census: model_census(services),
+            metadata: config.metadata(),
+        }
+    }
+
+    /// 保存活跃、受认证保护及识别为其他服务的目标和扫描汇总到 results/ 目录，返回结果文件名
+    pub fn save_results(services: &[OllamaService], summary: &ScanSummary) -> Result<String> {
         // 创建输出目录（如果不存在）
         std::fs::create_dir_all("results")?;
-        
+
         // 生成文件名，包含时间戳
         let timestamp = Local::now().format("%Y%m%d_%H%M%S");
         let filename = format!("results/ollama_scan_{}.json", timestamp);
-        
-        // 只保留活跃的服务
+
+        // 只保留活跃、受认证保护和识别为其他服务的目标
         let active_services: Vec<&OllamaService> = services.iter()
-            .filter(|service| service.is_active)
+            .filter(|service| service.is_active || service.is_protected() || service.is_other_service())
             .collect();
-        
+
         // 将结果序列化为JSON并格式化
         let json = serde_json::to_string_pretty(&active_services)?;
-        
+
         // 写入文件
         let mut file = File::create(&filename)?;
         file.write_all(json.as_bytes())?;
-        
+
+        // 汇总写入同名的 _summary 文件
+        let summary_json = serde_json::to_string_pretty(summary)?;
+        std::fs::write(format!("results/ollama_scan_{}_summary.json", timestamp), summary_json)?;
+
         Ok(filename)
     }
-    pub async fn scan_services(targets: Vec<Target>, timeout_secs: u64) -> Result<Vec<OllamaService>> {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(timeout_secs))
-            .build()?;
-            
-        let total = targets.len();
-        let pb = ProgressBar::new(total as u64);
-        pb.set_style(ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta}) {msg}")
-            .unwrap_or_else(|_| ProgressStyle::default_bar())
-            .progress_chars("#>-"));
-            
+
+    pub async fn scan_services(targets: Vec<Target>, config: &ScannerConfig) -> Result<Vec<OllamaService>> {
+        config.timeouts.validate()?;
+        let proxy = config.proxy.resolve()?;
+        let clients = Arc::new(ClientFactory::new(config, proxy.clone())?);
+        let prober = Arc::new(ConnectionProber::new(config, proxy)?);
+        let config = Arc::new(config.clone());
         let start_time = Instant::now();
-        
+
+        // 两阶段扫描时先用横幅检查筛掉不是 Ollama 的目标
+        // 通过横幅检查的目标沿用第一阶段实际通过的协议和请求方式
+        let (targets, mut services) = match &config.banner {
+            Some(banner) => {
+                let (passed, rejected) = Self::banner_phase(&clients, &prober, targets, &config, banner).await;
+                (passed.into_iter().map(|(target, plan)| (target, Some(plan))).collect(), rejected)
+            }
+            None => (targets.into_iter().map(|target| (target, None)).collect::<Vec<_>>(), Vec::new()),
+        };
+
+        let limiter = Arc::new(RateLimiter::new(config.max_concurrency, config.rate_limit));
+        let pb = progress_bar(targets.len());
+
         // 创建并发任务
         let handles: Vec<_> = targets
             .into_iter()
-            .map(|target| {
-                let client = client.clone();
+            .map(|(target, plan)| {
+                let clients = clients.clone();
+                let prober = prober.clone();
+                let config = config.clone();
+                let limiter = limiter.clone();
                 let pb = pb.clone();
                 tokio::spawn(async move {
                     pb.set_message(format!("Scanning {}", target.base_url()));
-                    let service = Self::scan_service(&client, target, timeout_secs).await;
+                    let plan = match plan {
+                        Some(plan) => plan,
+                        None => clients.plan(&prober, &target).await,
+                    };
+                    let service = Self::scan_service(&clients, &prober, target, plan, &config, &limiter).await;
                     pb.inc(1);
                     service
                 })
@@ -65,182 +295,730 @@ impl SimpleScanner {
             .collect();
 
         // 等待所有任务完成并收集结果
-        let mut services = Vec::with_capacity(total);
         for handle in handles {
-            if let Ok(result) = handle.await {
-                if let Ok(service) = result {
-                    services.push(service);
-                }
+            if let Ok(Ok(service)) = handle.await {
+                services.push(service);
             }
         }
-        
-        // 保存扫描结果
+
+        normalize_models(&mut services);
+
         let active_services = services.iter().filter(|s| s.is_active).count();
-        match Self::save_results(&services) {
-            Ok(filename) => {
-                pb.finish_with_message(format!(
-                    "Scan completed in {:.2?}. Found {}/{} active services. Results saved to {}",
-                    start_time.elapsed(),
-                    active_services,
-                    services.len(),
-                    filename
-                ));
-            }
-            Err(e) => {
-                pb.finish_with_message(format!(
-                    "Scan completed in {:.2?}. Found {}/{} active services. Failed to save results: {}",
-                    start_time.elapsed(),
-                    active_services,
-                    services.len(),
-                    e
-                ));
-            }
-        }
-        
+        let protected_services = services.iter().filter(|s| s.is_protected()).count();
+        pb.finish_with_message(format!(
+            "Scan completed in {:.2?}. Found {}/{} active services, {} protected.",
+            start_time.elapsed(),
+            active_services,
+            services.len(),
+            protected_services
+        ));
+
         Ok(services)
     }
-    
-    async fn scan_service(client: &Client, target: Target, timeout_secs: u64) -> Result<OllamaService> {
-        #[derive(Debug, Deserialize)]
-        struct ApiModelInfo {
-            name: String,
-            modified_at: String,
-            size: u64,
-            digest: String,
-            details: ApiModelDetails,
+
+    /// 第一阶段：并发检查根路径横幅，返回通过的目标及其请求方式和未通过目标的结果
+    async fn banner_phase(clients: &Arc<ClientFactory>, prober: &Arc<ConnectionProber>, targets: Vec<Target>, config: &Arc<ScannerConfig>, banner: &BannerCheck) -> (Vec<(Target, RequestPlan)>, Vec<OllamaService>) {
+        let limiter = Arc::new(RateLimiter::new(banner.concurrency, config.rate_limit));
+        let pb = progress_bar(targets.len());
+        let start_time = Instant::now();
+
+        let handles: Vec<_> = targets
+            .into_iter()
+            .map(|target| {
+                let clients = clients.clone();
+                let prober = prober.clone();
+                let config = config.clone();
+                let limiter = limiter.clone();
+                let timeout = banner.timeout;
+                let pb = pb.clone();
+                tokio::spawn(async move {
+                    pb.set_message(format!("Checking {}", target.base_url()));
+                    let plan = clients.plan(&prober, &target).await;
+                    let checked = Self::check_banner(&clients, &prober, target, plan, &config, timeout, &limiter).await;
+                    pb.inc(1);
+                    checked
+                })
+            })
+            .collect();
+
+        let mut passed = Vec::new();
+        let mut rejected = Vec::new();
+        for handle in handles {
+            let Ok((target, plan, endpoint, result)) = handle.await else {
+                continue;
+            };
+            match result {
+                Ok(()) => passed.push((target, plan)),
+                Err(failure) => rejected.push(OllamaService {
+                    egress: Some(prober.proxy_egress(&target).unwrap_or_else(|| "direct".to_string())),
+                    target,
+                    response_time: endpoint.response_time,
+                    confidence_score: Some(0.0),
+                    detection_details: DetectionDetails {
+                        endpoints_checked: vec![endpoint],
+                        ..Default::default()
+                    },
+                    failure: Some(failure),
+                    ..Default::default()
+                }),
+            }
         }
 
-        #[derive(Debug, Deserialize)]
-        struct ApiModelDetails {
-            format: String,
-            family: String,
-            families: Vec<String>,
-            parameter_size: String,
-            quantization_level: String,
+        pb.finish_with_message(format!(
+            "Banner check completed in {:.2?}. {}/{} targets passed.",
+            start_time.elapsed(),
+            passed.len(),
+            passed.len() + rejected.len()
+        ));
+        (passed, rejected)
+    }
+
+    /// 请求根路径并确认横幅，按重试策略重试，首次失败时按配置换另一种协议再试
+    ///
+    /// 需要认证的目标直接通过，由第二阶段记录认证信息；启用了检测器时，
+    /// 返回其他 2xx/3xx 响应的目标也通过，由第二阶段识别其他服务。
+    /// 换协议后才通过时返回换过协议的目标和请求方式，第二阶段直接使用可用的协议。
+    async fn check_banner(clients: &ClientFactory, prober: &ConnectionProber, target: Target, plan: RequestPlan, config: &ScannerConfig, timeout: std::time::Duration, limiter: &RateLimiter) -> (Target, RequestPlan, EndpointResult, std::result::Result<(), ScanFailure>) {
+        let (endpoint, result) = Self::check_banner_with_retries(&plan, config, timeout, limiter).await;
+        match &result {
+            Err(failure) if config.scheme_fallback && scheme_may_help(failure.kind) => {
+                let mut fallback = target.clone();
+                fallback.is_https = !target.is_https;
+                let fallback_plan = clients.plan(prober, &fallback).await;
+                let (fallback_endpoint, fallback_result) = Self::check_banner_with_retries(&fallback_plan, config, timeout, limiter).await;
+                // 都失败时保留原协议的失败原因
+                if fallback_result.is_ok() {
+                    return (fallback, fallback_plan, fallback_endpoint, fallback_result);
+                }
+                (target, plan, endpoint, result)
+            }
+            _ => (target, plan, endpoint, result),
+        }
+    }
+
+    async fn check_banner_with_retries(plan: &RequestPlan, config: &ScannerConfig, timeout: std::time::Duration, limiter: &RateLimiter) -> (EndpointResult, std::result::Result<(), ScanFailure>) {
+        let url = format!("{}/", plan.base_url);
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let start_time = Instant::now();
+            let (status_code, timing, result) = {
+                let _permit = limiter.acquire().await;
+                Self::fetch_banner(plan, &url, config, timeout).await
+            };
+
+            match &result {
+                Err(failure) if config.retry.should_retry(failure.kind, attempts) => {
+                    tokio::time::sleep(config.retry.backoff(attempts)).await;
+                }
+                _ => {
+                    let endpoint = EndpointResult {
+                        path: "/".to_string(),
+                        status_code,
+                        response_time: Some(start_time.elapsed().as_millis() as u64),
+                        success: result.is_ok(),
+                        error: result.as_ref().err().map(|f| f.message.clone()),
+                        attempts,
+                        timing: Some(timing),
+                    };
+                    return (endpoint, result);
+                }
+            }
+        }
+    }
+
+    /// 按重定向策略跟随跳转后检查最终响应，`timeout` 覆盖全部跳转，耗时取自最后一跳
+    async fn fetch_banner(plan: &RequestPlan, url: &str, config: &ScannerConfig, timeout: std::time::Duration) -> (Option<u16>, TimingBreakdown, std::result::Result<(), ScanFailure>) {
+        let deadline = Instant::now() + timeout;
+        let detectors = config.has_detectors();
+        let mut current = url.to_string();
+        let mut redirects = 0;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let hop_start = Instant::now();
+            let mut timing = TimingBreakdown::default();
+            let response = plan.get(&current).timeout(remaining).send().await;
+            timing.total_ms = Some(hop_start.elapsed().as_millis() as u64);
+            let response = match response {
+                Ok(response) => response,
+                Err(e) => return (None, timing, Err(classify_error(&e))),
+            };
+            timing.ttfb_ms = timing.total_ms;
+            let status = response.status().as_u16();
+
+            let next = response.status().is_redirection()
+                .then(|| response.headers().get(LOCATION))
+                .flatten()
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| response.url().join(location).ok())
+                .filter(|next| !config.redirect.same_host || next.host_str() == response.url().host_str());
+            match next {
+                Some(next) if redirects < config.redirect.max_redirects => {
+                    log::debug!("Following redirect {} -> {}", current, next);
+                    current = next.to_string();
+                    redirects += 1;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let result = match status {
+                200 => match Self::read_banner(response).await {
+                    Err(failure) if failure.kind == FailureKind::BannerMismatch && detectors => Ok(()),
+                    result => result,
+                },
+                status if is_denied(status) => Ok(()),
+                200..=399 if detectors => Ok(()),
+                status => Err(http_status_failure(status)),
+            };
+            timing.total_ms = Some(hop_start.elapsed().as_millis() as u64);
+            return (Some(status), timing, result);
         }
+    }
 
-        #[derive(Debug, Deserialize)]
-        struct TagsResponse {
-            models: Vec<ApiModelInfo>,
+    /// 只读取响应体开头的一小段查找横幅
+    async fn read_banner(mut response: reqwest::Response) -> std::result::Result<(), ScanFailure> {
+        let mut body = Vec::new();
+        while body.len() < BANNER_READ_LIMIT {
+            match response.chunk().await {
+                Ok(Some(chunk)) => body.extend_from_slice(&chunk),
+                Ok(None) => break,
+                Err(e) => return Err(classify_error(&e)),
+            }
+        }
+        if String::from_utf8_lossy(&body).contains(OLLAMA_BANNER) {
+            Ok(())
+        } else {
+            Err(ScanFailure {
+                kind: FailureKind::BannerMismatch,
+                message: format!("Root path did not return \"{}\"", OLLAMA_BANNER),
+            })
         }
-        
-        let url = format!("{}/api/tags", target.base_url());
+    }
+
+    /// 请求 /api/tags，按重定向策略逐跳跟随并解析最终响应
+    ///
+    /// 直连时每一跳都先由预连接建立连接，再在同一条连接上发送请求；经代理访问时由 reqwest 发送。
+    /// 耗时分解的各阶段都取自最后一跳，即给出最终响应的那条连接。
+    async fn fetch_tags(plan: &RequestPlan, prober: &ConnectionProber, target: &Target, url: &str, config: &ScannerConfig) -> TagsProbe {
         let start_time = std::time::Instant::now();
-        
-        match client.get(&url)
-            .timeout(Duration::from_secs(timeout_secs))
-            .send()
-            .await 
-        {
-            Ok(response) => {
-                let response_time = start_time.elapsed().as_millis() as u64;
-                let status = response.status().as_u16();
-                
-                if status == 200 {
-                    match response.json::<TagsResponse>().await {
-                        Ok(tags) => {
-                            let model_count = tags.models.len();
-                            
-                            // 如果models为空，认为服务无效
-                            if model_count == 0 {
-                                return Ok(OllamaService {
-                                    target,
-                                    is_active: false,
-                                    version: None,
-                                    models: vec![],
-                                    scan_time: chrono::Utc::now(),
-                                    response_time: Some(response_time),
-                                    confidence_score: Some(0.0),
-                                    detection_details: {
-                                        let mut details = DetectionDetails::default();
-                                        details.response_patterns.push("No models found in response".to_string());
-                                        details
-                                    },
-                                });
-                            }
-
-                            let models = tags.models.iter()
-                                .map(|m| ModelInfo {
-                                    name: m.name.clone(),
-                                    size: Some(m.size),
-                                    modified_at: Some(chrono::DateTime::parse_from_rfc3339(&m.modified_at)
-                                        .map(|dt| dt.with_timezone(&chrono::Utc))
-                                        .unwrap_or_else(|_| chrono::Utc::now())),
-                                    digest: Some(m.digest.clone()),
-                                    details: Some(ModelDetails {
-                                        format: Some(m.details.format.clone()),
-                                        family: Some(m.details.family.clone()),
-                                        families: Some(m.details.families.clone()),
-                                        parameter_size: Some(m.details.parameter_size.clone()),
-                                        quantization_level: Some(m.details.quantization_level.clone()),
-                                    }),
-                                })
-                                .collect();
-
-                            let mut detection_details = DetectionDetails::default();
-                            detection_details.response_patterns.push(format!(
-                                "Found {} models, format: {}", 
-                                model_count,
-                                tags.models.first()
-                                    .map(|m| m.details.format.as_str())
-                                    .unwrap_or("unknown")
-                            ));
-
-                            Ok(OllamaService {
-                                target,
-                                is_active: true,
-                                version: Some(tags.models.first()
-                                    .map(|m| m.details.format.clone())
-                                    .unwrap_or_default()),
-                                models,
-                                scan_time: chrono::Utc::now(),
-                                response_time: Some(response_time),
-                                confidence_score: Some(1.0),
-                                detection_details,
-                            })
-                        },
-                        Err(_) => {
-                            // 能访问但不是有效的Ollama服务
-                            Ok(OllamaService {
-                                target,
-                                is_active: false,
-                                version: None,
-                                models: vec![],
-                                scan_time: chrono::Utc::now(),
-                                response_time: Some(response_time),
-                                confidence_score: Some(0.0),
-                                detection_details: Default::default(),
-                            })
-                        }
+
+        let outcome = prober.probe(target).await;
+        let timeouts = outcome.timeouts;
+        let mut timing = outcome.timing;
+        let certificate = outcome.certificate;
+        let egress = outcome.egress;
+        let peer = outcome.peer;
+        let credential_scope = plan.credential.as_ref().map(|scoped| scoped.scope.clone());
+        let mut connection = match outcome.result {
+            Ok(connection) => connection,
+            Err(failure) => return TagsProbe {
+                status_code: None,
+                response_time: start_time.elapsed().as_millis() as u64,
+                timeouts,
+                timing,
+                certificate,
+                egress,
+                peer,
+                credential_scope,
+                redirect_chain: Vec::new(),
+                final_url: url.to_string(),
+                protection: None,
+                result: Err(failure),
+            },
+        };
+
+        let start_time = std::time::Instant::now();
+        let mut current = url.to_string();
+        let mut redirect_chain = Vec::new();
+        // 认证代理通常先跳转到登录页，每一跳都检查特征
+        let mut signature = None;
+        let response = loop {
+            let deadline = tokio::time::Instant::now() + config.timeouts.request;
+            let hop_start = Instant::now();
+            let response = match Self::send_hop(plan, connection.take(), &current, config.timeouts.request).await {
+                Ok(response) => response,
+                Err(failure) => break Err(failure),
+            };
+            timing.ttfb_ms = Some(hop_start.elapsed().as_millis() as u64);
+            let current_url = Url::parse(&current).ok();
+            signature = signature.or_else(|| proxy_signature(response.headers(), &current));
+
+            let next = (300..400).contains(&response.status())
+                .then(|| response.headers().get(LOCATION))
+                .flatten()
+                .and_then(|location| location.to_str().ok())
+                .zip(current_url.as_ref())
+                .and_then(|(location, current_url)| current_url.join(location).ok())
+                .filter(|next| !config.redirect.same_host || next.host_str() == current_url.as_ref().and_then(Url::host_str));
+            let redirects = redirect_chain.len().saturating_sub(1);
+            match next {
+                Some(next) if redirects < config.redirect.max_redirects => {
+                    log::debug!("Following redirect {} -> {}", current, next);
+                    if redirect_chain.is_empty() {
+                        redirect_chain.push(current.clone());
                     }
-                } else {
-                    // HTTP状态码不是200，不是有效的Ollama服务
-                    Ok(OllamaService {
-                        target,
-                        is_active: false,
-                        version: None,
-                        models: vec![],
-                        scan_time: chrono::Utc::now(),
-                        response_time: Some(response_time),
-                        confidence_score: Some(0.0),
-                        detection_details: Default::default(),
+                    current = next.to_string();
+                    redirect_chain.push(current.clone());
+
+                    let outcome = prober.probe(&Self::hop_target(target, &plan.base_url, &next)).await;
+                    timing = outcome.timing;
+                    match outcome.result {
+                        Ok(next_connection) => connection = next_connection,
+                        Err(failure) => break Err(failure),
+                    }
+                }
+                _ => break Ok((response, deadline, hop_start)),
+            }
+        };
+
+        let (status_code, protection, result) = match response {
+            Ok((response, deadline, hop_start)) => {
+                let status = response.status();
+                // 经代理访问时 407 来自扫描器自己的代理，不是目标的认证
+                let protection = detect_protection(status, response.headers(), signature, false)
+                    .filter(|_| !(status == 407 && egress != "direct"));
+                let result = if status == 200 {
+                    response.body(TAGS_READ_LIMIT, deadline).await.and_then(|body| {
+                        serde_json::from_slice::<TagsResponse>(&body).map_err(|e| ScanFailure {
+                            kind: FailureKind::BodyDecode,
+                            message: format!("error decoding response body: {}", e),
+                        })
                     })
+                } else {
+                    Err(http_status_failure(status))
+                };
+                timing.total_ms = Some(hop_start.elapsed().as_millis() as u64);
+                // 带凭据成功访问时不算被拦截
+                (Some(status), protection.filter(|_| result.is_err()), result)
+            }
+            Err(failure) => (None, None, Err(failure)),
+        };
+        let response_time = start_time.elapsed().as_millis() as u64;
+
+        TagsProbe {
+            status_code,
+            response_time,
+            timeouts,
+            timing,
+            certificate,
+            egress,
+            peer,
+            credential_scope,
+            redirect_chain,
+            final_url: current,
+            protection,
+            result,
+        }
+    }
+
+    /// 发送一跳 GET 请求：有预连接时在这条连接上发送，否则交给 reqwest
+    async fn send_hop(plan: &RequestPlan, connection: Option<Connection>, url: &str, timeout: std::time::Duration) -> std::result::Result<HttpResponse, ScanFailure> {
+        let Some(mut connection) = connection else {
+            return plan.get(url).timeout(timeout).send().await
+                .map(HttpResponse::Client)
+                .map_err(|e| classify_error(&e));
+        };
+
+        let request = plan.build(reqwest::Method::GET, url).map_err(|e| classify_error(&e))?;
+        tokio::time::timeout(timeout, connection.send(request)).await
+            .map_err(|_| ScanFailure {
+                kind: FailureKind::ReadTimeout,
+                message: format!("request timed out after {:?}", timeout),
+            })?
+            .map(HttpResponse::Connection)
+    }
+
+    /// 重定向目标对应的连接目标，跳到其他主机时不再使用虚拟主机名
+    fn hop_target(target: &Target, base_url: &str, url: &Url) -> Target {
+        let mut hop = target.clone();
+        hop.is_https = url.scheme() == "https";
+        hop.port = url.port_or_known_default().unwrap_or(target.port);
+        let same_host = Url::parse(base_url).ok().is_some_and(|base| base.host_str() == url.host_str());
+        if !same_host {
+            hop.host = url.host_str().unwrap_or_default().to_string();
+            hop.vhost = None;
+        }
+        hop
+    }
+
+    /// 按重试策略请求 /api/tags，每次尝试都经过全局限速，返回最后一次结果及尝试次数
+    async fn probe_with_retries(plan: &RequestPlan, prober: &ConnectionProber, target: &Target, config: &ScannerConfig, limiter: &RateLimiter) -> (TagsProbe, u32) {
+        let url = format!("{}/api/tags", plan.base_url);
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let probe = {
+                let _permit = limiter.acquire().await;
+                Self::fetch_tags(plan, prober, target, &url, config).await
+            };
+            match &probe.result {
+                Err(failure) if config.retry.should_retry(failure.kind, attempts) => {
+                    log::debug!("Retrying {} after {} (attempt {})", url, failure.kind, attempts);
+                    tokio::time::sleep(config.retry.backoff(attempts)).await;
                 }
+                _ => return (probe, attempts),
             }
-            Err(_) => {
-                let response_time = start_time.elapsed().as_millis() as u64;
-                // 网络错误，无法连接
-                Ok(OllamaService {
+        }
+    }
+
+    /// 发送检测器的一个请求，不重试也不跟随跳转
+    async fn fetch_probe(plan: &RequestPlan, probe: &ProbeRequest, config: &ScannerConfig, limiter: &RateLimiter) -> (EndpointResult, Option<ProbeResponse>) {
+        let url = format!("{}{}", plan.base_url, probe.path);
+        let _permit = limiter.acquire().await;
+        let start_time = Instant::now();
+
+        let mut request = plan.request(probe.method.clone(), &url).timeout(config.timeouts.request);
+        for (name, value) in &probe.headers {
+            request = request.header(name, value);
+        }
+        if let Some(body) = &probe.body {
+            request = request.body(body.clone());
+        }
+        let deadline = tokio::time::Instant::now() + config.timeouts.request;
+        let mut timing = TimingBreakdown::default();
+        let result = match request.send().await {
+            Ok(response) => {
+                timing.ttfb_ms = Some(start_time.elapsed().as_millis() as u64);
+                let status = response.status().as_u16();
+                let headers = response.headers().clone();
+                HttpResponse::Client(response).body(PROBE_READ_LIMIT, deadline).await
+                    .map(|body| ProbeResponse { status, headers, body: String::from_utf8_lossy(&body).into_owned() })
+            }
+            Err(e) => Err(classify_error(&e)),
+        };
+        timing.total_ms = Some(start_time.elapsed().as_millis() as u64);
+
+        let endpoint = EndpointResult {
+            path: probe.path.clone(),
+            status_code: result.as_ref().ok().map(|response| response.status),
+            response_time: timing.total_ms,
+            success: result.as_ref().is_ok_and(|response| response.status == 200),
+            error: result.as_ref().err().map(|failure| failure.message.clone()),
+            attempts: 1,
+            timing: Some(timing),
+        };
+        (endpoint, result.ok())
+    }
+
+    /// 按顺序运行检测器，多个检测器共用的请求只发送一次
+    ///
+    /// `first_only` 时第一个识别成功的检测器生效，否则返回全部识别成功的检测器。
+    async fn run_detectors<'a>(plan: &RequestPlan, detectors: impl Iterator<Item = &'a Arc<dyn Detector>>, first_only: bool, config: &ScannerConfig, limiter: &RateLimiter, details: &mut DetectionDetails) -> Vec<(String, Detection)> {
+        let mut responses: HashMap<ProbeRequest, Option<ProbeResponse>> = HashMap::new();
+        let mut detections = Vec::new();
+
+        for detector in detectors {
+            let probes = detector.probes();
+            for probe in &probes {
+                if !responses.contains_key(probe) {
+                    let (endpoint, response) = Self::fetch_probe(plan, probe, config, limiter).await;
+                    details.endpoints_checked.push(endpoint);
+                    responses.insert(probe.clone(), response);
+                }
+            }
+
+            let matched: Vec<Option<&ProbeResponse>> = probes.iter()
+                .map(|probe| responses.get(probe).and_then(Option::as_ref))
+                .collect();
+            if let Some(detection) = detector.detect(&matched) {
+                detections.push((detector.name().to_string(), detection));
+                if first_only {
+                    break;
+                }
+            }
+        }
+        detections
+    }
+
+    /// 请求 /api/version，返回 Ollama 版本号
+    async fn fetch_version(plan: &RequestPlan, config: &ScannerConfig, limiter: &RateLimiter, details: &mut DetectionDetails) -> Option<String> {
+        let (endpoint, response) = Self::fetch_probe(plan, &ProbeRequest::get("/api/version"), config, limiter).await;
+        details.endpoints_checked.push(endpoint);
+
+        let version: VersionResponse = serde_json::from_str(&response.filter(|r| r.status == 200)?.body).ok()?;
+        Some(version.version)
+    }
+
+    /// 请求 /api/ps，返回当前加载的模型；接口不可用时返回 None
+    async fn fetch_loaded_models(plan: &RequestPlan, config: &ScannerConfig, limiter: &RateLimiter, details: &mut DetectionDetails) -> Option<Vec<LoadedModel>> {
+        let (endpoint, response) = Self::fetch_probe(plan, &ProbeRequest::get("/api/ps"), config, limiter).await;
+        details.endpoints_checked.push(endpoint);
+
+        let ps: PsResponse = serde_json::from_str(&response.filter(|r| r.status == 200)?.body).ok()?;
+        Some(ps.models.into_iter()
+            .map(|m| LoadedModel {
+                name: m.name,
+                digest: m.digest,
+                size: m.size,
+                size_vram: m.size_vram,
+                expires_at: m.expires_at
+                    .and_then(|t| chrono::DateTime::parse_from_rfc3339(&t).ok())
+                    .map(|t| t.with_timezone(&chrono::Utc)),
+            })
+            .collect())
+    }
+
+    /// 逐个模型请求 /api/show，相同 digest 只请求一次，每个目标最多 `limit` 次
+    async fn enrich_models(plan: &RequestPlan, models: &mut [ModelInfo], limit: usize, config: &ScannerConfig, limiter: &RateLimiter, details: &mut DetectionDetails) {
+        let mut fetched: HashMap<String, Option<ModelMetadata>> = HashMap::new();
+        for model in models.iter_mut() {
+            let key = model.digest.clone().unwrap_or_else(|| model.name.clone());
+            if !fetched.contains_key(&key) {
+                if fetched.len() >= limit {
+                    continue;
+                }
+                let probe = ProbeRequest {
+                    method: reqwest::Method::POST,
+                    path: "/api/show".to_string(),
+                    headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+                    body: Some(serde_json::json!({ "model": model.name }).to_string()),
+                };
+                let (endpoint, response) = Self::fetch_probe(plan, &probe, config, limiter).await;
+                details.endpoints_checked.push(endpoint);
+                let metadata = response.filter(|r| r.status == 200)
+                    .and_then(|r| serde_json::from_str::<ShowResponse>(&r.body).ok())
+                    .map(ShowResponse::into_metadata);
+                fetched.insert(key.clone(), metadata);
+            }
+            model.metadata = fetched[&key].clone();
+        }
+    }
+
+    /// 第二轮请求使用的目标，协议与 /api/tags 实际成功的协议一致
+    fn confirmed_target(target: &Target, details: &DetectionDetails) -> Target {
+        let mut confirmed = target.clone();
+        if let Some(scheme) = &details.scheme {
+            confirmed.is_https = scheme == "https";
+        }
+        confirmed
+    }
+
+    fn endpoint_result(path: &str, probe: &TagsProbe, attempts: u32) -> EndpointResult {
+        EndpointResult {
+            path: path.to_string(),
+            status_code: probe.status_code,
+            response_time: Some(probe.response_time),
+            success: probe.result.is_ok(),
+            error: probe.result.as_ref().err().map(|f| f.message.clone()),
+            attempts,
+            timing: Some(probe.timing.clone()),
+        }
+    }
+
+    /// 探测单个目标，`plan` 是目标本身的请求方式，协议改变时才另建
+    async fn scan_service(clients: &ClientFactory, prober: &ConnectionProber, target: Target, mut plan: RequestPlan, config: &ScannerConfig, limiter: &RateLimiter) -> Result<OllamaService> {
+        let path = "/api/tags";
+        let mut detection_details = DetectionDetails::default();
+
+        let (mut probe, attempts) = Self::probe_with_retries(&plan, prober, &target, config, limiter).await;
+        detection_details.endpoints_checked.push(Self::endpoint_result(path, &probe, attempts));
+
+        // 换另一种协议再试一次，都失败时保留原协议的失败原因
+        if let Err(failure) = &probe.result {
+            if config.scheme_fallback && scheme_may_help(failure.kind) {
+                let mut fallback = target.clone();
+                fallback.is_https = !target.is_https;
+                log::debug!("Falling back from {} to {}", target.base_url(), fallback.base_url());
+
+                let fallback_plan = clients.plan(prober, &fallback).await;
+                let (fallback_probe, attempts) = Self::probe_with_retries(&fallback_plan, prober, &fallback, config, limiter).await;
+                detection_details.endpoints_checked.push(Self::endpoint_result(path, &fallback_probe, attempts));
+                if fallback_probe.result.is_ok() {
+                    probe = fallback_probe;
+                    plan = fallback_plan;
+                }
+            }
+        }
+
+        detection_details.redirect_chain = std::mem::take(&mut probe.redirect_chain);
+        let final_url = Url::parse(&probe.final_url).ok();
+        if probe.result.is_ok() {
+            detection_details.scheme = final_url.as_ref().map(|url| url.scheme().to_string());
+        }
+        if let Some(url) = &final_url {
+            let host = url.host_str().unwrap_or_default();
+            if host != target.host && Some(host) != target.vhost.as_deref() {
+                let final_host = format!("{}:{}", host, url.port_or_known_default().unwrap_or_default());
+                detection_details.response_patterns.push(format!("Redirected to other host {}", final_host));
+                detection_details.final_host = Some(final_host);
+            }
+        }
+
+        // 后续请求使用 /api/tags 实际成功的协议，跟随跳转改变了协议时另建请求方式
+        let confirmed = Self::confirmed_target(&target, &detection_details);
+        if confirmed.is_https != plan.is_https() {
+            plan = clients.plan(prober, &confirmed).await;
+        }
+
+        // 没有质询和认证代理特征的拒绝，根路径仍返回 Ollama 横幅时才算受保护的 Ollama
+        if let Some(status) = probe.status_code.filter(|status| probe.protection.is_none() && is_denied(*status)) {
+            let (endpoint, root) = Self::fetch_probe(&plan, &ProbeRequest::get("/"), config, limiter).await;
+            detection_details.endpoints_checked.push(endpoint);
+            if root.is_some_and(|root| root.status == 200 && root.body.contains(OLLAMA_BANNER)) {
+                probe.protection = detect_protection(status, &HeaderMap::new(), None, true);
+            }
+        }
+
+        let applied_timeouts = Some(probe.timeouts);
+        let timing = Some(probe.timing);
+        let tls_certificate = probe.certificate;
+        let egress = Some(probe.egress);
+        let peer_ip = probe.peer.map(|ip| ip.to_string());
+        let credential_scope = probe.credential_scope;
+        if let Some(protection) = &probe.protection {
+            detection_details.response_patterns.push(format!(
+                "Protected: HTTP {}{}{}",
+                protection.status_code,
+                protection.scheme.as_ref().map(|s| format!(", scheme {}", s)).unwrap_or_default(),
+                protection.proxy.as_ref().map(|p| format!(", behind {}", p)).unwrap_or_default(),
+            ));
+        }
+        let tags = match probe.result {
+            Ok(tags) => tags,
+            Err(failure) => {
+                // 有HTTP响应但不是Ollama时，尝试识别其他类型的服务
+                if probe.status_code.is_some() && probe.protection.is_none() && config.has_detectors() {
+                    // 模板排在内置检测器之前，避免被通用的 OpenAI 兼容检测抢先匹配
+                    let detectors = config.templates.iter().chain(config.detectors.iter());
+                    let detections = Self::run_detectors(&plan, detectors, true, config, limiter, &mut detection_details).await;
+                    if let Some((product, detection)) = detections.into_iter().next() {
+                        detection_details.response_patterns.push(format!("Detected {} with {} models", product, detection.models.len()));
+                        detection_details.authenticity_indicators.extend(detection.indicators);
+                        // is_active 只表示可访问的 Ollama，其他服务单独统计
+                        return Ok(OllamaService {
+                            target,
+                            is_active: false,
+                            product: Some(product),
+                            version: detection.version,
+                            models: detection.models,
+                            scan_time: chrono::Utc::now(),
+                            response_time: Some(probe.response_time),
+                            confidence_score: Some(detection.confidence),
+                            detection_details,
+                            applied_timeouts,
+                            timing,
+                            tls_certificate,
+                            egress,
+                            peer_ip,
+                            credential_scope,
+                            ..Default::default()
+                        });
+                    }
+                }
+
+                // 网络错误、HTTP状态码不是200或响应无法解析，不是有效的Ollama服务
+                return Ok(OllamaService {
                     target,
                     is_active: false,
                     version: None,
                     models: vec![],
                     scan_time: chrono::Utc::now(),
-                    response_time: Some(response_time),
+                    response_time: Some(probe.response_time),
                     confidence_score: Some(0.0),
-                    detection_details: Default::default(),
-                })
+                    detection_details,
+                    failure: Some(failure),
+                    applied_timeouts,
+                    timing,
+                    tls_certificate,
+                    egress,
+                    peer_ip,
+                    credential_scope,
+                    protection: probe.protection,
+                    ..Default::default()
+                });
             }
+        };
+
+        let model_count = tags.models.len();
+
+        // 如果models为空，认为服务无效
+        if model_count == 0 {
+            detection_details.response_patterns.push("No models found in response".to_string());
+            return Ok(OllamaService {
+                target,
+                is_active: false,
+                product: Some(OLLAMA_PRODUCT.to_string()),
+                version: None,
+                models: vec![],
+                scan_time: chrono::Utc::now(),
+                response_time: Some(probe.response_time),
+                confidence_score: Some(0.0),
+                detection_details,
+                applied_timeouts,
+                timing,
+                tls_certificate,
+                egress,
+                peer_ip,
+                credential_scope,
+                ..Default::default()
+            });
+        }
+
+        let mut models: Vec<ModelInfo> = tags.models.iter()
+            .map(|m| ModelInfo {
+                name: m.name.clone(),
+                size: Some(m.size),
+                modified_at: Some(chrono::DateTime::parse_from_rfc3339(&m.modified_at)
+                    .map(|dt| dt.with_timezone(&chrono::Utc))
+                    .unwrap_or_else(|_| chrono::Utc::now())),
+                digest: Some(m.digest.clone()),
+                details: Some(ModelDetails {
+                    format: Some(m.details.format.clone()),
+                    family: Some(m.details.family.clone()),
+                    families: Some(m.details.families.clone()),
+                    parameter_size: Some(m.details.parameter_size.clone()),
+                    quantization_level: Some(m.details.quantization_level.clone()),
+                }),
+                metadata: None,
+                canonical: None,
+            })
+            .collect();
+
+        let mut version = Self::fetch_version(&plan, config, limiter, &mut detection_details).await;
+
+        let mut loaded_models = None;
+        if config.probe_loaded_models {
+            loaded_models = Self::fetch_loaded_models(&plan, config, limiter, &mut detection_details).await;
+        }
+        if let Some(limit) = config.model_show_limit {
+            Self::enrich_models(&plan, &mut models, limit, config, limiter, &mut detection_details).await;
+        }
+
+        // 模板对 Ollama 同样生效，识别成功的模板补充识别依据
+        let templates = Self::run_detectors(&plan, config.templates.iter(), false, config, limiter, &mut detection_details).await;
+        for (name, detection) in templates {
+            detection_details.response_patterns.push(format!("Matched template {}", name));
+            detection_details.authenticity_indicators.extend(detection.indicators);
+            version = version.or(detection.version);
         }
+
+        detection_details.response_patterns.push(format!(
+            "Found {} models, format: {}",
+            model_count,
+            tags.models.first()
+                .map(|m| m.details.format.as_str())
+                .unwrap_or("unknown")
+        ));
+
+        Ok(OllamaService {
+            target,
+            is_active: true,
+            product: Some(OLLAMA_PRODUCT.to_string()),
+            version,
+            models,
+            scan_time: chrono::Utc::now(),
+            response_time: Some(probe.response_time),
+            confidence_score: Some(1.0),
+            detection_details,
+            applied_timeouts,
+            timing,
+            tls_certificate,
+            egress,
+            peer_ip,
+            credential_scope,
+            loaded_models,
+            ..Default::default()
+        })
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,370 @@
+use regex::Regex;
+use reqwest::Method;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use crate::{Result, OllamaError};
+use crate::scanner::{Detection, Detector, ProbeRequest, ProbeResponse};
+use crate::storage::{AuthenticityIndicator, ModelInfo};
+
+/// 模板文件：一个模板即一个检测器，全部请求的全部匹配条件都满足时识别成功
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TemplateFile {
+    name: String,
+    #[serde(default = "default_confidence")]
+    confidence: f64,
+    probes: Vec<ProbeSpec>,
+}
+
+fn default_confidence() -> f64 {
+    0.8
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProbeSpec {
+    path: String,
+    #[serde(default = "default_method")]
+    method: String,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    body: Option<String>,
+    #[serde(default)]
+    matchers: Vec<MatcherSpec>,
+    #[serde(default)]
+    extractors: Vec<ExtractorSpec>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum MatcherSpec {
+    Status { status: Vec<u16> },
+    Header { name: String, regex: String },
+    Body { regex: String },
+    /// 不写 regex 时只要求路径存在
+    Json { path: String, regex: Option<String> },
+}
+
+/// 从响应头、响应体或 JSON 路径取值，有捕获组时取第一个捕获组
+///
+/// 名为 model 的提取器取全部值作为模型列表，其余只取第一个值
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExtractorSpec {
+    /// 写入 indicator_type；名为 version / model 时同时填入服务版本和模型列表
+    name: String,
+    header: Option<String>,
+    json: Option<String>,
+    regex: Option<String>,
+    confidence: Option<f64>,
+    description: Option<String>,
+}
+
+/// 简化的 JSON 路径：`$.data[0].id`、`$.data[*].owned_by`
+#[derive(Debug, Clone)]
+enum Segment {
+    Key(String),
+    Index(usize),
+    Any,
+}
+
+fn parse_path(path: &str) -> Result<Vec<Segment>> {
+    let invalid = || OllamaError::InvalidInput(format!("Invalid JSON path '{}'", path));
+    let mut segments = Vec::new();
+    let trimmed = path.strip_prefix('$').unwrap_or(path);
+    for part in trimmed.split('.').filter(|part| !part.is_empty()) {
+        let (key, mut rest) = part.split_once('[').map(|(key, rest)| (key, Some(rest))).unwrap_or((part, None));
+        if !key.is_empty() {
+            segments.push(Segment::Key(key.to_string()));
+        }
+        while let Some(current) = rest {
+            let (index, next) = current.split_once(']').ok_or_else(invalid)?;
+            segments.push(match index {
+                "*" => Segment::Any,
+                index => Segment::Index(index.parse().map_err(|_| invalid())?),
+            });
+            rest = match next {
+                "" => None,
+                next => Some(next.strip_prefix('[').ok_or_else(invalid)?),
+            };
+        }
+    }
+    Ok(segments)
+}
+
+fn select<'a>(value: &'a Value, path: &[Segment]) -> Vec<&'a Value> {
+    let Some((segment, rest)) = path.split_first() else {
+        return vec![value];
+    };
+    match segment {
+        Segment::Key(key) => value.get(key).map(|v| select(v, rest)).unwrap_or_default(),
+        Segment::Index(index) => value.get(index).map(|v| select(v, rest)).unwrap_or_default(),
+        Segment::Any => value.as_array()
+            .map(|items| items.iter().flat_map(|v| select(v, rest)).collect())
+            .unwrap_or_default(),
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Source {
+    Header(String),
+    Body,
+    Json(Vec<Segment>),
+}
+
+impl Source {
+    fn values(&self, response: &ProbeResponse) -> Vec<String> {
+        match self {
+            Source::Header(name) => response.headers.get_all(name.as_str()).iter()
+                .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+                .collect(),
+            Source::Body => vec![response.body.clone()],
+            Source::Json(path) => serde_json::from_str::<Value>(&response.body).ok()
+                .map(|json| select(&json, path).into_iter()
+                    .map(|value| value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string()))
+                    .collect())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Status(Vec<u16>),
+    Value { source: Source, regex: Option<Regex> },
+}
+
+impl Matcher {
+    fn matches(&self, response: &ProbeResponse) -> bool {
+        match self {
+            Matcher::Status(status) => status.contains(&response.status),
+            Matcher::Value { source, regex } => source.values(response).iter()
+                .any(|value| regex.as_ref().is_none_or(|regex| regex.is_match(value))),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Extractor {
+    name: String,
+    source: Source,
+    regex: Option<Regex>,
+    confidence: Option<f64>,
+    description: String,
+}
+
+impl Extractor {
+    /// 全部取到的值，JSON 路径含 `[*]` 时可能有多个
+    fn extract(&self, response: &ProbeResponse) -> Vec<String> {
+        self.source.values(response).into_iter().filter_map(|value| match &self.regex {
+            Some(regex) => regex.captures(&value)
+                .map(|caps| caps.get(1).or_else(|| caps.get(0)).map_or("", |m| m.as_str()).to_string()),
+            None => Some(value),
+        })
+        .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TemplateProbe {
+    request: ProbeRequest,
+    matchers: Vec<Matcher>,
+    extractors: Vec<Extractor>,
+}
+
+/// 由模板文件定义的检测器
+#[derive(Debug, Clone)]
+pub struct TemplateDetector {
+    name: String,
+    confidence: f64,
+    probes: Vec<TemplateProbe>,
+}
+
+fn compile(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern).map_err(|e| OllamaError::InvalidInput(format!("Invalid regex '{}': {}", pattern, e)))
+}
+
+impl TemplateDetector {
+    /// 按扩展名解析 YAML (`.yaml`/`.yml`) 或 TOML (`.toml`) 模板
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let file: TemplateFile = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&content).map_err(|e| e.to_string()),
+            _ => serde_norway::from_str(&content).map_err(|e| e.to_string()),
+        }
+        .map_err(|e| OllamaError::ParseError(format!("Invalid template '{}': {}", path.display(), e)))?;
+
+        Self::compile(file).map_err(|e| OllamaError::InvalidInput(format!("Template '{}': {}", path.display(), e)))
+    }
+
+    fn compile(file: TemplateFile) -> Result<Self> {
+        if file.probes.is_empty() {
+            return Err(OllamaError::InvalidInput("at least one probe is required".to_string()));
+        }
+        let mut probes = Vec::new();
+        for spec in file.probes {
+            let method = Method::from_bytes(spec.method.to_uppercase().as_bytes())
+                .map_err(|_| OllamaError::InvalidInput(format!("Invalid method '{}'", spec.method)))?;
+            let path = if spec.path.starts_with('/') { spec.path } else { format!("/{}", spec.path) };
+
+            let matchers = spec.matchers.iter()
+                .map(|matcher| Ok(match matcher {
+                    MatcherSpec::Status { status } => Matcher::Status(status.clone()),
+                    MatcherSpec::Header { name, regex } => Matcher::Value {
+                        source: Source::Header(name.to_lowercase()),
+                        regex: Some(compile(regex)?),
+                    },
+                    MatcherSpec::Body { regex } => Matcher::Value { source: Source::Body, regex: Some(compile(regex)?) },
+                    MatcherSpec::Json { path, regex } => Matcher::Value {
+                        source: Source::Json(parse_path(path)?),
+                        regex: regex.as_deref().map(compile).transpose()?,
+                    },
+                }))
+                .collect::<Result<Vec<_>>>()?;
+
+            let extractors = spec.extractors.iter()
+                .map(|extractor| {
+                    let source = match (&extractor.header, &extractor.json) {
+                        (Some(header), None) => Source::Header(header.to_lowercase()),
+                        (None, Some(path)) => Source::Json(parse_path(path)?),
+                        (None, None) => Source::Body,
+                        (Some(_), Some(_)) => return Err(OllamaError::InvalidInput(format!(
+                            "Extractor '{}' cannot read both a header and a JSON path", extractor.name
+                        ))),
+                    };
+                    Ok(Extractor {
+                        name: extractor.name.clone(),
+                        source,
+                        regex: extractor.regex.as_deref().map(compile).transpose()?,
+                        confidence: extractor.confidence,
+                        description: extractor.description.clone()
+                            .unwrap_or_else(|| format!("Extracted from {} {}", method, path)),
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            probes.push(TemplateProbe {
+                request: ProbeRequest {
+                    method,
+                    path,
+                    headers: spec.headers.into_iter().collect(),
+                    body: spec.body,
+                },
+                matchers,
+                extractors,
+            });
+        }
+
+        Ok(Self {
+            name: file.name,
+            confidence: file.confidence,
+            probes,
+        })
+    }
+
+    /// 读取单个模板文件，或目录下全部 `.yaml`/`.yml`/`.toml` 文件（按文件名排序）
+    pub fn load(path: &Path) -> Result<Vec<Self>> {
+        if !path.is_dir() {
+            return Ok(vec![Self::from_file(path)?]);
+        }
+        let mut files: Vec<_> = std::fs::read_dir(path)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|file| matches!(file.extension().and_then(|ext| ext.to_str()), Some("yaml" | "yml" | "toml")))
+            .collect();
+        files.sort();
+        files.iter().map(|file| Self::from_file(file)).collect()
+    }
+}
+
+impl Detector for TemplateDetector {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn probes(&self) -> Vec<ProbeRequest> {
+        self.probes.iter().map(|probe| probe.request.clone()).collect()
+    }
+
+    fn detect(&self, responses: &[Option<&ProbeResponse>]) -> Option<Detection> {
+        let mut detection = Detection {
+            confidence: self.confidence,
+            ..Default::default()
+        };
+
+        for (probe, response) in self.probes.iter().zip(responses) {
+            let response = (*response)?;
+            if !probe.matchers.iter().all(|matcher| matcher.matches(response)) {
+                return None;
+            }
+            for extractor in &probe.extractors {
+                let values = extractor.extract(response);
+                let Some(value) = values.first().cloned() else {
+                    continue;
+                };
+                match extractor.name.as_str() {
+                    "version" => detection.version = Some(value.clone()),
+                    "model" => detection.models.extend(values.into_iter().map(|name| ModelInfo {
+                        name,
+                        size: None,
+                        modified_at: None,
+                        digest: None,
+                        details: None,
+                        metadata: None,
+                        canonical: None,
+                    })),
+                    _ => {}
+                }
+                detection.indicators.push(AuthenticityIndicator {
+                    indicator_type: extractor.name.clone(),
+                    value,
+                    confidence: extractor.confidence.unwrap_or(self.confidence),
+                    description: extractor.description.clone(),
+                });
+            }
+        }
+        Some(detection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn selected(value: &Value, path: &str) -> Vec<Value> {
+        select(value, &parse_path(path).unwrap()).into_iter().cloned().collect()
+    }
+
+    #[test]
+    fn parse_path_accepts_keys_indexes_and_wildcards() {
+        let value = json!({"data": [{"id": "a", "tags": ["x", "y"]}, {"id": "b", "tags": []}], "object": "list"});
+        assert_eq!(selected(&value, "$.object"), vec![json!("list")]);
+        assert_eq!(selected(&value, "data[1].id"), vec![json!("b")]);
+        assert_eq!(selected(&value, "$.data[*].id"), vec![json!("a"), json!("b")]);
+        assert_eq!(selected(&value, "$.data[0].tags[1]"), vec![json!("y")]);
+        assert_eq!(selected(&value, "$.data[*].tags[*]"), vec![json!("x"), json!("y")]);
+        assert_eq!(selected(&value, "$"), vec![value.clone()]);
+    }
+
+    #[test]
+    fn select_returns_nothing_for_missing_values() {
+        let value = json!({"data": [{"id": "a"}], "object": "list"});
+        assert!(selected(&value, "$.missing").is_empty());
+        assert!(selected(&value, "$.data[3].id").is_empty());
+        assert!(selected(&value, "$.object[*]").is_empty());
+        assert!(selected(&value, "$.data.id").is_empty());
+    }
+
+    #[test]
+    fn parse_path_rejects_malformed_indexes() {
+        for path in ["$.data[0", "$.data[x]", "$.data[-1]", "$.data[0]x"] {
+            assert!(parse_path(path).is_err(), "{} should be rejected", path);
+        }
+    }
+}
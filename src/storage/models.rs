@@ -1,14 +1,26 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+use crate::scanner::OLLAMA_PRODUCT;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Target {
     pub host: String,
     pub port: u16,
     pub source: String,
+    /// 输入文件中的原始国家值
     pub country: Option<String>,
+    /// 规范化后的 ISO 3166-1 alpha-2 代码
+    #[serde(default)]
+    pub country_code: Option<String>,
     pub is_https: bool,
+    /// 虚拟主机名：连接仍然发往 `host`，请求的 Host 头和 TLS SNI 使用该名称
+    #[serde(default)]
+    pub vhost: Option<String>,
+    /// 输入文件中未映射的额外列
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
 }
 
 impl Target {
@@ -20,6 +32,26 @@ impl Target {
         let protocol = if self.is_https { "https" } else { "http" };
         format!("{}://{}:{}", protocol, self.host, self.port)
     }
+
+    /// Host 头的取值，使用默认端口时省略端口
+    pub fn host_header(&self) -> String {
+        let host = self.vhost.as_deref().unwrap_or(&self.host);
+        let default_port = if self.is_https { 443 } else { 80 };
+        if self.port == default_port {
+            host.to_string()
+        } else {
+            format!("{}:{}", host, self.port)
+        }
+    }
+
+    /// 主机为IP地址时返回该地址（兼容带方括号的IPv6）
+    pub fn ip_addr(&self) -> Option<IpAddr> {
+        self.host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse()
+            .ok()
+    }
 }
 
 impl Default for Target {
@@ -29,7 +61,10 @@ impl Default for Target {
             port: 80,
             source: String::new(),
             country: None,
+            country_code: None,
             is_https: false,
+            vhost: None,
+            tags: HashMap::new(),
         }
     }
 }
@@ -38,6 +73,9 @@ impl Default for Target {
 pub struct OllamaService {
     pub target: Target,
     pub is_active: bool,
+    /// 识别出的服务类型：ollama 或匹配的检测器名称
+    #[serde(default)]
+    pub product: Option<String>,
     pub version: Option<String>,
     pub models: Vec<ModelInfo>,
     pub scan_time: DateTime<Utc>,
@@ -45,6 +83,50 @@ pub struct OllamaService {
     pub confidence_score: Option<f64>,
     #[serde(default)]
     pub detection_details: DetectionDetails,
+    #[serde(default)]
+    pub network: Option<NetworkInfo>,
+    #[serde(default)]
+    pub failure: Option<ScanFailure>,
+    #[serde(default)]
+    pub applied_timeouts: Option<AppliedTimeouts>,
+    /// 主探测请求的耗时分解
+    #[serde(default)]
+    pub timing: Option<TimingBreakdown>,
+    /// HTTPS 目标的服务端证书
+    #[serde(default)]
+    pub tls_certificate: Option<CertificateInfo>,
+    /// 出口路径：direct 或使用的代理（不含认证信息）
+    #[serde(default)]
+    pub egress: Option<String>,
+    /// 直连时实际连接的IP地址，经代理访问或未能建连时为 None
+    #[serde(default)]
+    pub peer_ip: Option<String>,
+    /// 请求时使用的凭据范围，凭据本身不会写入结果
+    #[serde(default)]
+    pub credential_scope: Option<String>,
+    /// 被认证网关拦截时的认证信息
+    #[serde(default)]
+    pub protection: Option<AuthProtection>,
+    /// /api/ps 返回的已加载模型，未探测时为 None
+    #[serde(default)]
+    pub loaded_models: Option<Vec<LoadedModel>>,
+}
+
+impl OllamaService {
+    /// 服务存在但需要认证才能访问
+    pub fn is_protected(&self) -> bool {
+        !self.is_active && self.protection.is_some()
+    }
+
+    /// 网络归属查询使用的地址：IP 目标本身，主机名目标取扫描时连接的地址
+    pub fn resolved_ip(&self) -> Option<IpAddr> {
+        self.target.ip_addr().or_else(|| self.peer_ip.as_deref().and_then(|ip| ip.parse().ok()))
+    }
+
+    /// 由检测器识别为 Ollama 以外的服务
+    pub fn is_other_service(&self) -> bool {
+        !self.is_active && self.failure.is_none() && self.product.as_deref().is_some_and(|p| p != OLLAMA_PRODUCT)
+    }
 }
 
 impl Default for OllamaService {
@@ -52,16 +134,158 @@ impl Default for OllamaService {
         Self {
             target: Default::default(),
             is_active: false,
+            product: None,
             version: None,
             models: vec![],
             scan_time: chrono::Utc::now(),
             response_time: None,
             confidence_score: None,
             detection_details: Default::default(),
+            network: None,
+            failure: None,
+            applied_timeouts: None,
+            timing: None,
+            tls_certificate: None,
+            egress: None,
+            peer_ip: None,
+            credential_scope: None,
+            protection: None,
+            loaded_models: None,
         }
     }
 }
 
+/// 认证保护信息，来自 401/403/407 响应、认证质询头或认证代理特征
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthProtection {
+    pub status_code: u16,
+    /// 质询中的认证方式，如 Basic、Bearer、Digest
+    pub scheme: Option<String>,
+    pub realm: Option<String>,
+    /// 识别出的认证代理，如 oauth2-proxy、Cloudflare Access
+    pub proxy: Option<String>,
+}
+
+/// 服务端 TLS 证书摘要
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    /// 主体备用名称 (DNS、IP、邮箱、URI)
+    #[serde(default)]
+    pub sans: Vec<String>,
+    pub not_before: DateTime<Utc>,
+    pub not_after: DateTime<Utc>,
+    pub self_signed: bool,
+    pub expired: bool,
+}
+
+/// 请求耗时分解（毫秒）
+///
+/// `/api/tags` 的 DNS、建连和 TLS 握手来自预连接，HTTP请求随后在同一条连接上发送，
+/// 因此 `ttfb_ms`（发送请求到收到响应头）不含建连和握手，减去一个往返即近似于服务端处理时间。
+/// 其余请求由 HTTP 客户端发送，只记录 `ttfb_ms` 和 `total_ms`，新建连接时 `ttfb_ms` 包含建连和握手。
+/// `total_ms` 到读完响应体为止。
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct TimingBreakdown {
+    pub dns_ms: Option<u64>,
+    pub connect_ms: Option<u64>,
+    pub tls_ms: Option<u64>,
+    pub ttfb_ms: Option<u64>,
+    pub total_ms: Option<u64>,
+}
+
+/// 探测时实际使用的超时
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct AppliedTimeouts {
+    pub connect_ms: u64,
+    pub tls_handshake_ms: Option<u64>,
+    pub request_ms: u64,
+    /// 是否根据网段RTT统计调整
+    #[serde(default)]
+    pub adaptive: bool,
+    /// 统计RTT所用的网段
+    pub network: Option<String>,
+}
+
+/// 扫描失败的分类
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    DnsFailure,
+    ConnectionRefused,
+    ConnectionReset,
+    ConnectTimeout,
+    ReadTimeout,
+    TlsHandshake,
+    HttpStatus,
+    BodyDecode,
+    /// 根路径响应中没有 Ollama 横幅
+    BannerMismatch,
+    Other,
+}
+
+impl FailureKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureKind::DnsFailure => "dns_failure",
+            FailureKind::ConnectionRefused => "connection_refused",
+            FailureKind::ConnectionReset => "connection_reset",
+            FailureKind::ConnectTimeout => "connect_timeout",
+            FailureKind::ReadTimeout => "read_timeout",
+            FailureKind::TlsHandshake => "tls_handshake",
+            FailureKind::HttpStatus => "http_status",
+            FailureKind::BodyDecode => "body_decode",
+            FailureKind::BannerMismatch => "banner_mismatch",
+            FailureKind::Other => "other",
+        }
+    }
+}
+
+impl std::str::FromStr for FailureKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim() {
+            "dns_failure" => Ok(FailureKind::DnsFailure),
+            "connection_refused" => Ok(FailureKind::ConnectionRefused),
+            "connection_reset" => Ok(FailureKind::ConnectionReset),
+            "connect_timeout" => Ok(FailureKind::ConnectTimeout),
+            "read_timeout" => Ok(FailureKind::ReadTimeout),
+            "tls_handshake" => Ok(FailureKind::TlsHandshake),
+            "http_status" => Ok(FailureKind::HttpStatus),
+            "body_decode" => Ok(FailureKind::BodyDecode),
+            "banner_mismatch" => Ok(FailureKind::BannerMismatch),
+            "other" => Ok(FailureKind::Other),
+            other => Err(format!("unknown failure kind '{}'", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanFailure {
+    pub kind: FailureKind,
+    pub message: String,
+}
+
+/// 离线 GeoIP / ASN 数据库给出的网络归属信息
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct NetworkInfo {
+    pub ip: Option<String>,
+    pub country_code: Option<String>,
+    pub asn: Option<u32>,
+    pub organization: Option<String>,
+    /// 没有查询时的原因
+    #[serde(default)]
+    pub lookup_skipped: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelInfo {
     pub name: String,
@@ -69,6 +293,66 @@ pub struct ModelInfo {
     pub modified_at: Option<DateTime<Utc>>,
     pub digest: Option<String>,
     pub details: Option<ModelDetails>,
+    /// /api/show 返回的详细信息，未启用补全时为 None
+    #[serde(default)]
+    pub metadata: Option<ModelMetadata>,
+    /// 规范化后的模型身份，相同 digest 的模型一致
+    #[serde(default)]
+    pub canonical: Option<CanonicalModel>,
+}
+
+/// 与命名方式无关的模型身份
+///
+/// `llama3:latest`、`llama3:8b` 和 `library/llama3:8b-instruct-q4_0` 规范化后
+/// 都是 family `llama3`、parameter_size `8b`、quantization `q4_0`。
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct CanonicalModel {
+    pub family: String,
+    /// instruct、chat、code、text 等，`latest` 不算变体
+    pub variant: Option<String>,
+    /// 小写并去掉多余小数位，如 `8b`、`137m`、`8x7b`
+    pub parameter_size: Option<String>,
+    /// 小写，如 `q4_0`、`q4_k_m`、`f16`
+    pub quantization: Option<String>,
+}
+
+/// 模型普查中的一项：相同 digest 的模型合并为一项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelCensusEntry {
+    /// 没有 digest 的模型（其他类型的服务）按规范化身份合并
+    pub digest: Option<String>,
+    pub canonical: CanonicalModel,
+    /// 出现过的全部原始名称
+    pub names: Vec<String>,
+    /// 提供该模型的服务数
+    pub services: usize,
+}
+
+/// 来自 /api/show 的模型详细信息
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ModelMetadata {
+    /// 模型支持的最大上下文长度
+    pub context_length: Option<u64>,
+    /// completion、embedding、vision、tools 等，旧版本 Ollama 不返回
+    pub capabilities: Option<Vec<String>>,
+    /// 许可证首行，通常是许可证名称
+    pub license: Option<String>,
+    /// Modelfile 中覆盖的参数，如 num_ctx、stop（可重复）
+    #[serde(default)]
+    pub parameters: BTreeMap<String, Vec<String>>,
+}
+
+/// 当前加载在内存中的模型
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadedModel {
+    pub name: String,
+    pub digest: Option<String>,
+    /// 占用的总内存（字节）
+    pub size: Option<u64>,
+    /// 其中位于显存的部分（字节）
+    pub size_vram: Option<u64>,
+    /// 空闲后被卸载的时间
+    pub expires_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -90,6 +374,15 @@ pub struct DetectionDetails {
     pub response_patterns: Vec<String>,
     #[serde(default)]
     pub authenticity_indicators: Vec<AuthenticityIndicator>,
+    /// 发生跳转时依次请求的URL，首项为初始URL
+    #[serde(default)]
+    pub redirect_chain: Vec<String>,
+    /// 实际成功响应的协议 (http/https)
+    #[serde(default)]
+    pub scheme: Option<String>,
+    /// 跟随跳转到其他主机时给出最终响应的主机 (`host:port`)，结果来自该主机而不是目标本身
+    #[serde(default)]
+    pub final_host: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -99,6 +392,11 @@ pub struct EndpointResult {
     pub response_time: Option<u64>,
     pub success: bool,
     pub error: Option<String>,
+    /// 包括重试在内的尝试次数
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default)]
+    pub timing: Option<TimingBreakdown>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -155,6 +453,39 @@ pub struct ScanReport {
     pub performance_metrics: Vec<PerformanceMetrics>,
 }
 
+/// 一次扫描的汇总，与结果文件一同保存
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanSummary {
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub total_targets: usize,
+    pub active_services: usize,
+    /// 需要认证才能访问的服务数
+    #[serde(default)]
+    pub protected_services: usize,
+    /// 检测器识别出的其他服务，按产品统计
+    #[serde(default)]
+    pub other_services: BTreeMap<String, usize>,
+    pub error_details: Vec<ErrorDetail>,
+    /// 活跃服务上的模型按 digest 合并后的统计，按服务数降序
+    #[serde(default)]
+    pub model_census: Vec<ModelCensusEntry>,
+    #[serde(default)]
+    pub metadata: ScanMetadata,
+}
+
+/// 扫描环境信息，便于网络归属方核实扫描来源
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ScanMetadata {
+    /// 出站连接绑定的源地址
+    pub source_address: Option<String>,
+    pub source_interface: Option<String>,
+    /// 探测请求使用的 User-Agent 和额外请求头
+    pub user_agent: Option<String>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PerformanceSummary {
     pub fastest_service: Option<Target>,
@@ -163,10 +494,4 @@ pub struct PerformanceSummary {
     pub average_response_time: f64,
     pub total_models_found: usize,
     pub unique_model_names: Vec<String>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct OllamaAssetCsv {
-    pub country: String,
-    pub link: String,
 }
\ No newline at end of file
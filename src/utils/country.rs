@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use crate::storage::Target;
+
+/// 随crate打包的国家别名表：ISO 3166-1 alpha-2 代码及其英文、中文名称和常见别名
+const COUNTRY_TABLE: &str = include_str!("../../data/countries.tsv");
+
+fn aliases() -> &'static HashMap<String, &'static str> {
+    static ALIASES: OnceLock<HashMap<String, &'static str>> = OnceLock::new();
+    ALIASES.get_or_init(|| {
+        let mut aliases = HashMap::new();
+        for line in COUNTRY_TABLE.lines().filter(|l| !l.starts_with('#') && !l.trim().is_empty()) {
+            let (code, names) = line.split_once('\t').unwrap_or((line, ""));
+            aliases.insert(normalize_key(code), code);
+            for name in names.split('|') {
+                aliases.insert(normalize_key(name), code);
+            }
+        }
+        aliases
+    })
+}
+
+/// 忽略大小写、首尾空白以及连续空白
+fn normalize_key(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// 将国家名称或代码映射为 ISO 3166-1 alpha-2 代码，无法识别时返回 None
+pub fn normalize_country(raw: &str) -> Option<&'static str> {
+    aliases().get(&normalize_key(raw)).copied()
+}
+
+/// 统计未能映射的原始国家值，按出现次数降序排列
+pub fn unmapped_countries(targets: &[Target]) -> Vec<(String, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for target in targets.iter().filter(|t| t.country_code.is_none()) {
+        if let Some(country) = &target.country {
+            *counts.entry(country.as_str()).or_default() += 1;
+        }
+    }
+
+    let mut unmapped: Vec<_> = counts.into_iter()
+        .map(|(country, count)| (country.to_string(), count))
+        .collect();
+    unmapped.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    unmapped
+}
@@ -1,25 +1,127 @@
-use csv::Reader;
-use std::fs::File;
-use std::io::{BufReader, Read};
+use csv::{Reader, StringRecord};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::Read;
 use url::Url;
 use crate::{Result, OllamaError};
-use crate::storage::{Target, OllamaAssetCsv};
+use crate::storage::Target;
+use crate::utils::{InputSource, normalize_country};
+
+/// CSV 列映射，指定哪些列包含 URL、主机、端口、协议、国家和虚拟主机名
+///
+/// 未被映射的列会作为标签保存到 `Target.tags` 中。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColumnMapping {
+    pub url: Option<String>,
+    pub host: Option<String>,
+    pub port: Option<String>,
+    pub scheme: Option<String>,
+    pub country: Option<String>,
+    /// 虚拟主机名 (Host 头 / SNI)
+    pub vhost: Option<String>,
+}
+
+impl Default for ColumnMapping {
+    /// 默认映射对应 ollama 资产格式 (country,link)
+    fn default() -> Self {
+        Self {
+            url: Some("link".to_string()),
+            host: None,
+            port: None,
+            scheme: None,
+            country: Some("country".to_string()),
+            vhost: None,
+        }
+    }
+}
+
+impl ColumnMapping {
+    /// 从 TOML 配置文件加载列映射
+    pub fn from_file(path: &str) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        toml::from_str(&content).map_err(|e| {
+            OllamaError::ParseError(format!("Invalid column mapping '{}': {}", path, e))
+        })
+    }
+
+    /// 根据表头解析出各列的下标
+    fn resolve(&self, headers: &StringRecord) -> Result<ColumnIndices> {
+        let find = |column: &Option<String>| -> Result<Option<usize>> {
+            match column {
+                Some(name) => headers.iter()
+                    .position(|h| h.trim() == name)
+                    .map(Some)
+                    .ok_or_else(|| OllamaError::InvalidInput(format!("Column '{}' not found in CSV header", name))),
+                None => Ok(None),
+            }
+        };
+
+        // URL 列缺失时允许退回到主机列
+        let url = match find(&self.url) {
+            Ok(index) => index,
+            Err(e) if self.host.is_none() => return Err(e),
+            Err(_) => None,
+        };
+
+        let indices = ColumnIndices {
+            url,
+            host: find(&self.host)?,
+            port: find(&self.port)?,
+            scheme: find(&self.scheme)?,
+            country: find(&self.country).unwrap_or_else(|e| {
+                log::warn!("{}, targets will have no country", e);
+                None
+            }),
+            vhost: find(&self.vhost)?,
+        };
+
+        if indices.url.is_none() && indices.host.is_none() {
+            return Err(OllamaError::InvalidInput("Column mapping needs a URL or host column".to_string()));
+        }
+
+        Ok(indices)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ColumnIndices {
+    url: Option<usize>,
+    host: Option<usize>,
+    port: Option<usize>,
+    scheme: Option<usize>,
+    country: Option<usize>,
+    vhost: Option<usize>,
+}
+
+impl ColumnIndices {
+    fn is_mapped(&self, index: usize) -> bool {
+        [self.url, self.host, self.port, self.scheme, self.country, self.vhost].contains(&Some(index))
+    }
+}
 
 pub struct CsvParser;
 
 impl CsvParser {
     /// 解析ollama资产CSV格式 (country,link)
     pub fn parse_ollama_assets<R: Read>(reader: R) -> Result<Vec<Target>> {
+        Self::parse_with_mapping(reader, &ColumnMapping::default())
+    }
+
+    /// 按列映射解析任意资产CSV，额外的列作为标签保留
+    pub fn parse_with_mapping<R: Read>(reader: R, mapping: &ColumnMapping) -> Result<Vec<Target>> {
         let mut csv_reader = Reader::from_reader(reader);
+        let headers = csv_reader.headers()?.clone();
+        let indices = mapping.resolve(&headers)?;
         let mut targets = Vec::new();
         
-        for (line_num, result) in csv_reader.deserialize::<OllamaAssetCsv>().enumerate() {
+        for (line_num, result) in csv_reader.records().enumerate() {
             match result {
-                Ok(asset) => {
-                    match Self::parse_url_to_target(&asset.link, &asset.country, line_num + 1) {
+                Ok(record) => {
+                    match Self::parse_record(&record, &headers, &indices, line_num + 1) {
                         Ok(target) => targets.push(target),
                         Err(e) => {
-                            log::warn!("Failed to parse URL '{}' at line {}: {}", asset.link, line_num + 1, e);
+                            log::warn!("Failed to parse target at line {}: {}", line_num + 1, e);
                             continue;
                         }
                     }
@@ -34,8 +136,41 @@ impl CsvParser {
         Ok(targets)
     }
     
+    /// 将一行记录转换为Target，URL列优先，否则使用主机/端口/协议列
+    fn parse_record(record: &StringRecord, headers: &StringRecord, indices: &ColumnIndices, line_num: usize) -> Result<Target> {
+        let field = |index: Option<usize>| {
+            index.and_then(|i| record.get(i))
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+
+        let country = field(indices.country);
+        let mut target = match field(indices.url) {
+            Some(url) => Self::parse_url_to_target(url, country, line_num)?,
+            None => {
+                let host = field(indices.host).ok_or_else(|| {
+                    OllamaError::ParseError("No URL or host value".to_string())
+                })?;
+                Self::parse_host_to_target(host, field(indices.port), field(indices.scheme), country, line_num)?
+            }
+        };
+        // 与目标主机相同的虚拟主机名没有意义
+        target.vhost = field(indices.vhost)
+            .map(str::to_lowercase)
+            .filter(|vhost| *vhost != target.host);
+
+        target.tags = headers.iter()
+            .zip(record.iter())
+            .enumerate()
+            .filter(|(i, (_, value))| !indices.is_mapped(*i) && !value.trim().is_empty())
+            .map(|(_, (name, value))| (name.trim().to_string(), value.trim().to_string()))
+            .collect::<HashMap<_, _>>();
+
+        Ok(target)
+    }
+
     /// 解析URL字符串为Target结构体
-    fn parse_url_to_target(url_str: &str, country: &str, line_num: usize) -> Result<Target> {
+    fn parse_url_to_target(url_str: &str, country: Option<&str>, line_num: usize) -> Result<Target> {
         let url = Url::parse(url_str).map_err(|e| {
             OllamaError::ParseError(format!("Invalid URL '{}': {}", url_str, e))
         })?;
@@ -56,15 +191,58 @@ impl CsvParser {
             host,
             port,
             source: format!("Ollama-Assets-Line-{}", line_num),
-            country: Some(country.to_string()),
+            country: country.map(str::to_string),
+            country_code: country.and_then(normalize_country).map(str::to_string),
             is_https,
+            ..Default::default()
         })
     }
     
+    /// 由主机、端口、协议列构造Target，协议缺省为http
+    fn parse_host_to_target(host: &str, port: Option<&str>, scheme: Option<&str>, country: Option<&str>, line_num: usize) -> Result<Target> {
+        let is_https = match scheme.map(|s| s.to_ascii_lowercase()) {
+            None => port == Some("443"),
+            Some(s) if s == "https" || s == "tls" || s == "ssl" => true,
+            Some(s) if s == "http" => false,
+            Some(s) => return Err(OllamaError::ParseError(format!("Unsupported scheme '{}'", s))),
+        };
+
+        let port = match port {
+            Some(port) => port.parse::<u16>().map_err(|e| {
+                OllamaError::ParseError(format!("Invalid port '{}': {}", port, e))
+            })?,
+            None if is_https => 443,
+            None => 80,
+        };
+
+        Ok(Target {
+            host: Self::bracket_ipv6(host),
+            port,
+            source: format!("Ollama-Assets-Line-{}", line_num),
+            country: country.map(str::to_string),
+            country_code: country.and_then(normalize_country).map(str::to_string),
+            is_https,
+            ..Default::default()
+        })
+    }
+
+    /// 与URL解析结果保持一致，IPv6地址使用方括号
+    fn bracket_ipv6(host: &str) -> String {
+        if host.contains(':') && !host.starts_with('[') {
+            format!("[{}]", host)
+        } else {
+            host.to_string()
+        }
+    }
+
+    /// 从文件解析，支持 `-`（标准输入）以及 `.gz`/`.zst` 压缩文件
     pub fn parse_from_file(file_path: &str) -> Result<Vec<Target>> {
-        let file = File::open(file_path).map_err(OllamaError::Io)?;
-        let reader = BufReader::new(file);
-        Self::parse_ollama_assets(reader)
+        Self::parse_from_file_with_mapping(file_path, &ColumnMapping::default())
+    }
+
+    pub fn parse_from_file_with_mapping(file_path: &str, mapping: &ColumnMapping) -> Result<Vec<Target>> {
+        let reader = InputSource::open(file_path)?;
+        Self::parse_with_mapping(reader, mapping)
     }
     
     pub fn validate_targets(targets: &[Target]) -> Vec<&Target> {
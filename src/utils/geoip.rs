@@ -0,0 +1,76 @@
+use maxminddb::{geoip2, Reader};
+use std::net::IpAddr;
+use crate::Result;
+use crate::storage::{NetworkInfo, OllamaService};
+
+/// 基于本地 MaxMind / DB-IP `.mmdb` 文件的离线 GeoIP 与 ASN 补全
+///
+/// 每个数据库都会尝试按国家和 ASN 两种结构查询，因此 Country、City、ASN
+/// 以及同时包含两类信息的数据库可以任意组合。
+pub struct GeoIpEnricher {
+    databases: Vec<Reader<Vec<u8>>>,
+}
+
+impl GeoIpEnricher {
+    pub fn open<P: AsRef<str>>(paths: &[P]) -> Result<Self> {
+        let mut databases = Vec::with_capacity(paths.len());
+        for path in paths {
+            let path = path.as_ref();
+            let reader = Reader::open_readfile(path)?;
+            log::info!("Loaded {} database from {}", reader.metadata.database_type, path);
+            databases.push(reader);
+        }
+        Ok(Self { databases })
+    }
+
+    /// 查询单个IP，先命中的数据库优先
+    pub fn lookup(&self, ip: IpAddr) -> Option<NetworkInfo> {
+        let mut info = NetworkInfo {
+            ip: Some(ip.to_string()),
+            ..Default::default()
+        };
+
+        for reader in &self.databases {
+            if info.country_code.is_none() {
+                if let Ok(record) = reader.lookup::<geoip2::Country>(ip) {
+                    info.country_code = record.country
+                        .or(record.registered_country)
+                        .and_then(|c| c.iso_code)
+                        .map(str::to_string);
+                }
+            }
+            if info.asn.is_none() {
+                if let Ok(record) = reader.lookup::<geoip2::Asn>(ip) {
+                    info.asn = record.autonomous_system_number;
+                    info.organization = record.autonomous_system_organization.map(str::to_string);
+                }
+            }
+        }
+
+        if info.country_code.is_none() && info.asn.is_none() {
+            return None;
+        }
+        Some(info)
+    }
+
+    /// 为所有结果补全网络归属信息，主机名目标使用扫描时连接的地址
+    ///
+    /// 主机名目标经代理访问或未能建连时没有可查询的地址，在 `network` 中记录跳过的原因。
+    pub fn enrich(&self, services: &mut [OllamaService]) -> usize {
+        let mut enriched = 0;
+        for service in services.iter_mut() {
+            let Some(ip) = service.resolved_ip() else {
+                service.network = Some(NetworkInfo {
+                    lookup_skipped: Some(format!("no address for hostname {} (proxied or not connected)", service.target.host)),
+                    ..Default::default()
+                });
+                continue;
+            };
+            if let Some(info) = self.lookup(ip) {
+                service.network = Some(info);
+                enriched += 1;
+            }
+        }
+        enriched
+    }
+}
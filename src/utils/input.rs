@@ -0,0 +1,73 @@
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+use crate::{Result, OllamaError};
+
+/// 标准输入的占位路径
+pub const STDIN_PATH: &str = "-";
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// 输入数据的压缩格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// 根据文件扩展名判断压缩格式
+    pub fn from_path(path: &str) -> Option<Self> {
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("gz") => Some(Compression::Gzip),
+            Some(ext) if ext.eq_ignore_ascii_case("zst") => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    /// 根据数据开头的魔数判断压缩格式
+    pub fn from_magic(header: &[u8]) -> Self {
+        if header.starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        } else if header.starts_with(ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+pub struct InputSource;
+
+impl InputSource {
+    /// 打开输入源，`-` 表示标准输入，`.gz`/`.zst` 会被透明解压
+    ///
+    /// 没有可识别扩展名的输入（包括标准输入）按魔数探测压缩格式。
+    pub fn open(path: &str) -> Result<Box<dyn Read>> {
+        let raw: Box<dyn Read> = if path == STDIN_PATH {
+            Box::new(io::stdin())
+        } else {
+            Box::new(File::open(path).map_err(OllamaError::Io)?)
+        };
+        let mut reader = BufReader::new(raw);
+
+        let compression = match Compression::from_path(path) {
+            Some(compression) => compression,
+            None => Compression::from_magic(reader.fill_buf()?),
+        };
+
+        Self::decompress(reader, compression)
+    }
+
+    /// 按指定格式包装解压器
+    pub fn decompress<R: BufRead + 'static>(reader: R, compression: Compression) -> Result<Box<dyn Read>> {
+        Ok(match compression {
+            Compression::None => Box::new(reader),
+            Compression::Gzip => Box::new(BufReader::new(MultiGzDecoder::new(reader))),
+            Compression::Zstd => Box::new(BufReader::new(zstd::Decoder::with_buffer(reader)?)),
+        })
+    }
+}
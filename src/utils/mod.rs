@@ -1,3 +1,11 @@
+pub mod country;
 pub mod csv_parser;
+pub mod geoip;
+pub mod input;
+pub mod model_identity;
 
-pub use csv_parser::*;
\ No newline at end of file
+pub use country::*;
+pub use csv_parser::*;
+pub use geoip::*;
+pub use input::*;
+pub use model_identity::*;
@@ -0,0 +1,250 @@
+use std::collections::{BTreeMap, BTreeSet};
+use crate::storage::{CanonicalModel, ModelCensusEntry, ModelInfo, OllamaService};
+
+/// 不带含义的标签和名称片段
+const IGNORED_TOKENS: &[&str] = &["latest", "gguf"];
+
+/// 不带量化级别前缀的精度名称
+const PRECISIONS: &[&str] = &["f16", "f32", "bf16", "fp16", "fp32", "fp8", "int4", "int8"];
+
+/// `8b`、`0.5b`、`137m`、`8x7b`；`1048k` 之类的上下文长度不算
+fn is_parameter_size(token: &str) -> bool {
+    let size = match token.split_once('x') {
+        Some((experts, size)) if !experts.is_empty() && experts.chars().all(|c| c.is_ascii_digit()) => size,
+        _ => token,
+    };
+    size.strip_suffix(['b', 'm'])
+        .is_some_and(|number| !number.is_empty() && number.parse::<f64>().is_ok())
+}
+
+/// `q4_0`、`q4_k_m`、`iq3_xs`、`f16`
+fn is_quantization(token: &str) -> bool {
+    let level = token.strip_prefix("iq").or_else(|| token.strip_prefix('q'));
+    level.is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit())) || PRECISIONS.contains(&token)
+}
+
+/// 统一参数规模的写法：`8.0B` -> `8b`
+fn normalize_size(size: &str) -> Option<String> {
+    let size = size.trim().to_lowercase();
+    if size.is_empty() {
+        return None;
+    }
+    let unit = size.chars().last()?;
+    let number = &size[..size.len() - unit.len_utf8()];
+    match number.strip_suffix(".0").or_else(|| number.strip_suffix(".00")) {
+        Some(whole) => Some(format!("{}{}", whole, unit)),
+        None => Some(size),
+    }
+}
+
+fn normalize_quantization(quantization: &str) -> Option<String> {
+    let quantization = quantization.trim().to_lowercase();
+    (!quantization.is_empty() && quantization != "unknown").then_some(quantization)
+}
+
+/// 从模型名称和 /api/tags 的摘要信息推出规范化身份
+///
+/// 去掉镜像仓库和 `library/` 前缀后，名称按 `-` 切分：参数规模之前的部分是 family，
+/// 其余片段中量化级别和参数规模单独识别，剩下的作为变体。摘要信息中的参数规模和
+/// 量化级别来自模型文件本身，优先于名称。
+pub fn canonical_model(model: &ModelInfo) -> CanonicalModel {
+    let name = model.name.trim().to_lowercase();
+    let last = name.rsplit('/').next().unwrap_or(&name);
+    let (repo, tag) = last.split_once(':').unwrap_or((last, ""));
+
+    let mut family = Vec::new();
+    let mut variant = Vec::new();
+    let mut parameter_size = None;
+    let mut quantization = None;
+    let mut in_family = true;
+    let tokens = repo.split('-').map(|t| (t, true)).chain(tag.split('-').map(|t| (t, false)));
+    for (token, from_repo) in tokens {
+        if token.is_empty() || IGNORED_TOKENS.contains(&token) {
+            continue;
+        }
+        if is_parameter_size(token) {
+            parameter_size.get_or_insert_with(|| token.to_string());
+            in_family = false;
+        } else if is_quantization(token) {
+            quantization.get_or_insert_with(|| token.to_string());
+            in_family = false;
+        } else if from_repo && in_family {
+            family.push(token);
+        } else {
+            in_family = false;
+            variant.push(token);
+        }
+    }
+
+    let details = model.details.as_ref();
+    CanonicalModel {
+        family: if family.is_empty() {
+            details.and_then(|d| d.family.clone()).unwrap_or_else(|| repo.to_string())
+        } else {
+            family.join("-")
+        },
+        variant: (!variant.is_empty()).then(|| variant.join("-")),
+        parameter_size: details.and_then(|d| d.parameter_size.as_deref()).and_then(normalize_size)
+            .or_else(|| parameter_size.as_deref().and_then(normalize_size)),
+        quantization: details.and_then(|d| d.quantization_level.as_deref()).and_then(normalize_quantization)
+            .or(quantization),
+    }
+}
+
+/// 合并同一模型文件的多个名称：每个字段取第一个非空值，变体取最长的
+fn merge(identities: &[CanonicalModel]) -> CanonicalModel {
+    let first = |field: fn(&CanonicalModel) -> &Option<String>| identities.iter().find_map(|c| field(c).clone());
+    CanonicalModel {
+        family: identities.iter().map(|c| c.family.clone()).min_by_key(|f| f.len()).unwrap_or_default(),
+        variant: identities.iter().filter_map(|c| c.variant.clone()).max_by_key(|v| v.len()),
+        parameter_size: first(|c| &c.parameter_size),
+        quantization: first(|c| &c.quantization),
+    }
+}
+
+/// 为全部模型填写规范化身份，相同 digest 的模型使用合并后的同一身份
+pub fn normalize_models(services: &mut [OllamaService]) {
+    let mut by_digest: BTreeMap<String, Vec<CanonicalModel>> = BTreeMap::new();
+    for model in services.iter_mut().flat_map(|s| s.models.iter_mut()) {
+        let canonical = canonical_model(model);
+        if let Some(digest) = &model.digest {
+            by_digest.entry(digest.clone()).or_default().push(canonical.clone());
+        }
+        model.canonical = Some(canonical);
+    }
+
+    let merged: BTreeMap<String, CanonicalModel> = by_digest.into_iter()
+        .map(|(digest, identities)| (digest, merge(&identities)))
+        .collect();
+    for model in services.iter_mut().flat_map(|s| s.models.iter_mut()) {
+        if let Some(canonical) = model.digest.as_ref().and_then(|digest| merged.get(digest)) {
+            model.canonical = Some(canonical.clone());
+        }
+    }
+}
+
+/// 普查的合并依据：有 digest 时用 digest，否则用规范化身份
+type CensusKey = (Option<String>, Option<CanonicalModel>);
+
+/// 统计活跃服务上的模型，相同 digest 合并，没有 digest 的按规范化身份合并
+pub fn model_census(services: &[OllamaService]) -> Vec<ModelCensusEntry> {
+    let mut entries: BTreeMap<CensusKey, ModelCensusEntry> = BTreeMap::new();
+    for service in services.iter().filter(|s| s.is_active) {
+        let mut counted = BTreeSet::new();
+        for model in &service.models {
+            let canonical = model.canonical.clone().unwrap_or_else(|| canonical_model(model));
+            let key = match &model.digest {
+                Some(digest) => (Some(digest.clone()), None),
+                None => (None, Some(canonical.clone())),
+            };
+            let entry = entries.entry(key.clone()).or_insert_with(|| ModelCensusEntry {
+                digest: model.digest.clone(),
+                canonical,
+                names: Vec::new(),
+                services: 0,
+            });
+            if !entry.names.contains(&model.name) {
+                entry.names.push(model.name.clone());
+            }
+            // 同一服务上的多个别名只计一次
+            if counted.insert(key) {
+                entry.services += 1;
+            }
+        }
+    }
+
+    let mut census: Vec<ModelCensusEntry> = entries.into_values().collect();
+    for entry in &mut census {
+        entry.names.sort();
+    }
+    census.sort_by(|a, b| b.services.cmp(&a.services).then_with(|| a.canonical.cmp(&b.canonical)));
+    census
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::ModelDetails;
+
+    fn model(name: &str, digest: Option<&str>) -> ModelInfo {
+        ModelInfo {
+            name: name.to_string(),
+            size: None,
+            modified_at: None,
+            digest: digest.map(str::to_string),
+            details: None,
+            metadata: None,
+            canonical: None,
+        }
+    }
+
+    fn identity(family: &str, variant: Option<&str>, parameter_size: Option<&str>, quantization: Option<&str>) -> CanonicalModel {
+        CanonicalModel {
+            family: family.to_string(),
+            variant: variant.map(str::to_string),
+            parameter_size: parameter_size.map(str::to_string),
+            quantization: quantization.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn names_are_parsed_into_identities() {
+        assert_eq!(canonical_model(&model("llama3:latest", None)), identity("llama3", None, None, None));
+        assert_eq!(canonical_model(&model("llama3:8b", None)), identity("llama3", None, Some("8b"), None));
+        assert_eq!(
+            canonical_model(&model("library/llama3:8b-instruct-q4_0", None)),
+            identity("llama3", Some("instruct"), Some("8b"), Some("q4_0")),
+        );
+        assert_eq!(
+            canonical_model(&model("registry.example.com/mistral:8x7b", None)),
+            identity("mistral", None, Some("8x7b"), None),
+        );
+        // 上下文长度不是参数规模
+        assert_eq!(
+            canonical_model(&model("llama3-gradient:1048k", None)),
+            identity("llama3-gradient", Some("1048k"), None, None),
+        );
+    }
+
+    #[test]
+    fn tags_details_take_precedence_over_the_name() {
+        let mut latest = model("llama3:latest", None);
+        latest.details = Some(ModelDetails {
+            format: Some("gguf".to_string()),
+            family: Some("llama".to_string()),
+            families: None,
+            parameter_size: Some("8.0B".to_string()),
+            quantization_level: Some("Q4_0".to_string()),
+        });
+        assert_eq!(canonical_model(&latest), identity("llama3", None, Some("8b"), Some("q4_0")));
+    }
+
+    #[test]
+    fn models_with_the_same_digest_share_the_merged_identity() {
+        let mut services = vec![
+            OllamaService {
+                is_active: true,
+                models: vec![model("llama3:latest", Some("sha256:a")), model("llama3:8b", Some("sha256:a"))],
+                ..Default::default()
+            },
+            OllamaService {
+                is_active: true,
+                models: vec![model("library/llama3:8b-instruct-q4_0", Some("sha256:a")), model("qwen2:7b", Some("sha256:b"))],
+                ..Default::default()
+            },
+        ];
+        normalize_models(&mut services);
+
+        let merged = identity("llama3", Some("instruct"), Some("8b"), Some("q4_0"));
+        for model in services.iter().flat_map(|s| &s.models).filter(|m| m.digest.as_deref() == Some("sha256:a")) {
+            assert_eq!(model.canonical.as_ref(), Some(&merged), "{}", model.name);
+        }
+        assert_eq!(services[1].models[1].canonical, Some(identity("qwen2", None, Some("7b"), None)));
+
+        let census = model_census(&services);
+        assert_eq!(census.len(), 2);
+        assert_eq!(census[0].canonical, merged);
+        assert_eq!(census[0].services, 2);
+        assert_eq!(census[0].names, ["library/llama3:8b-instruct-q4_0", "llama3:8b", "llama3:latest"]);
+    }
+}